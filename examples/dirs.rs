@@ -64,6 +64,6 @@ fn main() {
     args.next().unwrap();
     let path = args.next().unwrap();
     let path = Path::new(&path);
-    let f = build_forest(&path);
+    let f = build_forest(path);
     print(&f);
 }
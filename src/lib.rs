@@ -1,8 +1,9 @@
 use std::boxed::Box;
+use std::io;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ForestEdge {
     Trailing,
     Leading,
@@ -123,9 +124,9 @@ struct CursorIterator<T: CursorLike> {
 impl<T: CursorLike> Iterator for CursorIterator<T> {
     type Item = <T as CursorLike>::Item;
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.cursor.current();
+        let item = self.cursor.current()?;
         self.cursor.move_next();
-        item
+        Some(item)
     }
 }
 
@@ -136,7 +137,7 @@ struct RawCursor<T> {
 
 impl<T> Clone for RawCursor<T> {
     fn clone(&self) -> Self {
-        RawCursor { ..*self }
+        *self
     }
 }
 
@@ -172,7 +173,6 @@ impl<T> RawCursor<T> {
         is_leading(self.edge)
     }
 
-    #[allow(dead_code)]
     fn is_trailing(&self) -> bool {
         is_trailing(self.edge)
     }
@@ -218,6 +218,80 @@ impl<T> RawCursor<T> {
         self.pivot();
     }
 
+    unsafe fn child_count(&self) -> usize {
+        let mut count = 0;
+        let last = self.trailing_of();
+        let mut child = self.leading_of().next();
+        while child != last {
+            count += 1;
+            child.move_next_child();
+        }
+        count
+    }
+
+    // Number of nodes in the subtree rooted at this node, itself included.
+    unsafe fn subtree_size(&self) -> usize {
+        let mut count = 0;
+        let last = self.trailing_of();
+        let mut position = self.leading_of();
+        while position != last {
+            if position.is_leading() {
+                count += 1;
+            }
+            position.move_next();
+        }
+        count
+    }
+
+    // The trailing edge of the parent, or the forest's end for a top-level
+    // node.
+    unsafe fn parent(&self) -> Self {
+        let mut position = self.trailing_of();
+        loop {
+            position.move_next();
+            if position.is_trailing() {
+                return position;
+            }
+            position = position.trailing_of();
+        }
+    }
+
+    unsafe fn reverse_children(&self) {
+        let last = self.trailing_of();
+        let first = self.leading_of().next();
+        if first == last {
+            return;
+        }
+        // Move every following child to the front in turn.
+        let mut child = first.trailing_of().next();
+        while child != last {
+            let next = child.trailing_of().next();
+            self.leading_of().next().splice_subtree(child);
+            child = next;
+        }
+    }
+
+    // The leading edge of the following sibling, if any.
+    unsafe fn next_sibling(&self) -> Option<Self> {
+        let mut next = self.leading_of();
+        next.move_next_child();
+        if next.is_leading() {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    // The leading edge of the preceding sibling, if any.
+    unsafe fn prev_sibling(&self) -> Option<Self> {
+        let prev = self.leading_of().prev();
+        if prev.is_trailing() {
+            Some(prev.leading_of())
+        } else {
+            None
+        }
+    }
+
     unsafe fn current<'a>(&self) -> Option<&'a T> {
         Some((*self.node).data.assume_init_ref())
     }
@@ -234,7 +308,7 @@ impl<T> RawCursor<T> {
             edge: ForestEdge::Leading,
         };
         set_next(&self.prev(), &result);
-        set_next(&result.next(), &self);
+        set_next(&result.next(), self);
         result
     }
 
@@ -254,13 +328,19 @@ impl<T> RawCursor<T> {
                 } else {
                     position.move_next();
                 }
-                stack_depth = std::cmp::max(0, stack_depth - 1);
+                stack_depth = stack_depth.saturating_sub(1);
             }
         }
         last
     }
 
     unsafe fn erase(self) -> Self {
+        self.extract().0
+    }
+
+    // Unlinks the node like `erase` but moves its value out instead of
+    // dropping it.
+    unsafe fn extract(self) -> (Self, T) {
         /*
             https://github.com/stlab/libraries/blob/c86c645eb6696360b49a2ff05aa25aa07f5b94d2/stlab/forest.hpp
             NOTE (sparent) : After the first call to set_next() the invariants of the forest are
@@ -280,16 +360,14 @@ impl<T> RawCursor<T> {
             set_next(&leading_prior, &trailing_next);
         }
 
-        {
-            std::ptr::drop_in_place((*self.node).data.as_mut_ptr());
-            Box::from_raw(self.node);
-        }
+        let node = Box::from_raw(self.node);
 
-        if self.is_leading() {
+        let next = if self.is_leading() {
             leading_prior.next()
         } else {
             trailing_next
-        }
+        };
+        (next, node.data.assume_init())
     }
 
     unsafe fn splice(&mut self, first: RawCursor<T>, last: RawCursor<T>) -> RawCursor<T> {
@@ -299,7 +377,7 @@ impl<T> RawCursor<T> {
 
         let back = last.prev();
 
-        set_next(&first.prev_child(), &last);
+        set_next(&first.prev(), &last);
 
         set_next(&self.prev(), &first);
         set_next(&back, self);
@@ -307,6 +385,11 @@ impl<T> RawCursor<T> {
         first
     }
 
+    // Moves the subtree rooted at `node` to just before this position.
+    unsafe fn splice_subtree(mut self, node: RawCursor<T>) {
+        self.splice(node.leading_of(), node.trailing_of().next());
+    }
+
     unsafe fn next(&self) -> Self {
         let mut clone = RawCursor { ..*self };
         clone.move_next();
@@ -327,6 +410,7 @@ impl<T> RawCursor<T> {
         clone
     }
 
+    #[allow(dead_code)]
     unsafe fn prev_child(&self) -> Self {
         let mut clone = RawCursor { ..*self };
         clone.move_prev_child();
@@ -354,13 +438,74 @@ unsafe fn set_next<T>(x: &RawCursor<T>, y: &RawCursor<T>) {
     *(*y.node).base.link_mut(y.edge, NextPrior::Prior) = x.node;
 }
 
+// Whether the children of `pattern` match, in order, a subsequence of the
+// children of `parent`, with each matched child's children matching in turn.
+unsafe fn embeds<T, P, F: Fn(&T, &P) -> bool>(parent: RawCursor<T>, pattern: RawCursor<P>, matches: &F) -> bool {
+    let mut child = parent.leading_of().next();
+    let child_end = parent.trailing_of();
+    let mut pattern_child = pattern.leading_of().next();
+    let pattern_child_end = pattern.trailing_of();
+    while pattern_child != pattern_child_end {
+        loop {
+            if child == child_end {
+                return false;
+            }
+            let found = matches(child.current().unwrap(), pattern_child.current().unwrap())
+                && embeds(child, pattern_child, matches);
+            child.move_next_child();
+            if found {
+                break;
+            }
+        }
+        pattern_child.move_next_child();
+    }
+    true
+}
+
+// Tracks the depth during a fullorder walk inside the crate. A trailing edge
+// without a matching leading edge, which only a corrupted forest can produce,
+// is reported instead of underflowing. It isn't public, so the examples keep
+// counting depth by hand.
+#[derive(Default)]
+struct DepthTracker {
+    depth: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct UnbalancedEdge;
+
+impl std::fmt::Display for UnbalancedEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "trailing edge without a matching leading edge")
+    }
+}
+
+impl std::error::Error for UnbalancedEdge {}
+
+impl DepthTracker {
+    // Returns the depth of the node whose edge is visited.
+    fn visit(&mut self, edge: ForestEdge) -> Result<usize, UnbalancedEdge> {
+        match edge {
+            ForestEdge::Leading => {
+                self.depth += 1;
+                Ok(self.depth - 1)
+            }
+            ForestEdge::Trailing => {
+                self.depth = self.depth.checked_sub(1).ok_or(UnbalancedEdge)?;
+                Ok(self.depth)
+            }
+        }
+    }
+}
+
 struct EdgeCursor<'a, T: 'a> {
     edge: ForestEdge,
     cursor: Cursor<'a, T>,
 }
 
 impl<'a, T> EdgeCursor<'a, T> {
-    fn new(edge: ForestEdge, cursor: Cursor<'a, T>) -> Self {
+    fn new(edge: ForestEdge, mut cursor: Cursor<'a, T>) -> Self {
+        cursor.find_edge(edge);
         Self { edge, cursor }
     }
 
@@ -387,83 +532,336 @@ impl<'a, T> CursorLike for EdgeCursor<'a, T> {
     }
 }
 
-pub struct Cursor<'a, T: 'a> {
-    forest: &'a Forest<T>,
-    cursor: RawCursor<T>,
+pub struct EdgeIter<'a, T: 'a> {
+    front: EdgeCursor<'a, T>,
+    // Just past the last edge not yet yielded from the back.
+    back: EdgeCursor<'a, T>,
+    // Known when the forest's cached size is, as every node has exactly one
+    // edge of each kind.
+    remaining: Option<usize>,
 }
 
-impl<'a, T> PartialEq for Cursor<'a, T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.cursor == other.cursor
+impl<'a, T> Iterator for EdgeIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front.cursor == self.back.cursor {
+            return None;
+        }
+        let item = self.front.current()?;
+        self.front.move_next();
+        self.yielded();
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (0, None),
+        }
     }
 }
 
-impl<'a, T> Eq for Cursor<'a, T> {}
+impl<'a, T> DoubleEndedIterator for EdgeIter<'a, T> {
+    // Both ends only ever rest on edges of the iterated kind, so the back
+    // cursor reaches the front one before it could pass it.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front.cursor == self.back.cursor {
+            return None;
+        }
+        self.back.move_prev();
+        let item = self.back.current()?;
+        self.yielded();
+        Some(item)
+    }
+}
 
-impl<'a, T> Cursor<'a, T> {
-    pub fn leading_of(&mut self) {
-        self.cursor = self.cursor.leading_of();
+impl<'a, T> EdgeIter<'a, T> {
+    fn yielded(&mut self) {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
     }
+}
 
-    pub fn trailing_of(&mut self) {
-        self.cursor = self.cursor.trailing_of();
+/// The values of a forest in preorder, as returned by `Forest::iter`.
+pub struct Iter<'a, T: 'a> {
+    inner: EdgeIter<'a, T>,
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
     }
+}
 
-    pub fn move_next(&mut self) {
-        unsafe { self.cursor.move_next(); }
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
     }
 
-    pub fn move_prev(&mut self) {
-        unsafe { self.cursor.move_prev(); }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
     }
+}
 
-    pub fn edge(&self) -> ForestEdge {
-        self.cursor.edge
+/// Every edge of a forest in order with the value of its node, as returned
+/// by `Forest::fullorder`.
+pub struct Edges<'a, T: 'a> {
+    cursor: Cursor<'a, T>,
+}
+
+impl<'a, T> Iterator for Edges<'a, T> {
+    type Item = (ForestEdge, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next()
     }
+}
 
-    pub fn current(&self) -> Option<&'a T> {
+/// The values of a forest in postorder, each node after all of its children.
+pub struct Postorder<'a, T: 'a> {
+    inner: EdgeIter<'a, T>,
+}
+
+impl<'a, T> DoubleEndedIterator for Postorder<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, T> Iterator for Postorder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// The values of a node's immediate children, as returned by
+/// `Cursor::children`.
+pub struct ChildIter<'a, T: 'a> {
+    forest: &'a Forest<T>,
+    position: RawCursor<T>,
+}
+
+impl<'a, T> Iterator for ChildIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.position.is_leading() {
+            return None;
+        }
+        let value = Cursor { forest: self.forest, cursor: self.position }.current();
+        unsafe { self.position.move_next_child(); }
+        value
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Keep {
+    Yield,
+    Skip,
+    SkipSubtree,
+}
+
+pub struct PreorderFiltered<'a, T: 'a, F> {
+    cursor: Cursor<'a, T>,
+    f: F,
+}
+
+impl<'a, T, F: FnMut(&T) -> Keep> Iterator for PreorderFiltered<'a, T, F> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = self.cursor.current()?;
+            let keep = (self.f)(value);
+            if keep == Keep::SkipSubtree {
+                // Jump past the children straight to the node's trailing edge.
+                self.cursor.trailing_of();
+            }
+            self.cursor.move_next();
+            self.cursor.find_edge(ForestEdge::Leading);
+            if keep == Keep::Yield {
+                return Some(value);
+            }
+        }
+    }
+}
+
+/// The values of a forest level by level, as returned by
+/// `Forest::breadth_first`.
+pub struct Bfs<'a, T: 'a> {
+    forest: &'a Forest<T>,
+    // The first not yet visited child of each queued sibling group.
+    queue: std::collections::VecDeque<RawCursor<T>>,
+}
+
+impl<'a, T> Iterator for Bfs<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut position = self.queue.pop_front()?;
         unsafe {
-            if self.cursor.equal_node(&self.forest.unsafe_root()) {
-                None
-            } else {
-                self.cursor.current()
+            let first_child = position.next();
+            if first_child.is_leading() {
+                self.queue.push_back(first_child);
             }
+            let value = Cursor { forest: self.forest, cursor: position }.current();
+            position.move_next_child();
+            if position.is_leading() {
+                self.queue.push_front(position);
+            }
+            value
         }
     }
+}
 
-    fn find_edge(&mut self, edge: ForestEdge) {
-        while self.cursor.edge != edge {
-            self.move_next();
+/// The preorder values whose node and ancestors all pass the predicate.
+pub struct Filter<'a, T: 'a, F> {
+    cursor: Cursor<'a, T>,
+    pred: F,
+}
+
+impl<'a, T, F: Fn(&T) -> bool> Iterator for Filter<'a, T, F> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let value = self.cursor.current()?;
+            let passed = (self.pred)(value);
+            if !passed {
+                self.cursor.trailing_of();
+            }
+            self.cursor.move_next();
+            self.cursor.find_edge(ForestEdge::Leading);
+            if passed {
+                return Some(value);
+            }
         }
     }
+}
 
-    fn find_edge_reverse(&mut self, edge: ForestEdge) {
-        while self.cursor.edge != edge {
-            self.move_prev();
+// Counts children during the single edge walk instead of scanning each
+// node's children. A node's count is only known at its trailing edge, so
+// nodes are buffered from their leading edge until then and yielded in
+// preorder as soon as they and every node before them are complete.
+struct PreorderWithChildCounts<'a, T: 'a> {
+    cursor: Cursor<'a, T>,
+    pending: std::collections::VecDeque<(&'a T, usize)>,
+    // Preorder indices of the nodes whose trailing edge hasn't been reached.
+    open: Vec<usize>,
+    // Preorder index of the front of `pending`.
+    yielded: usize,
+}
+
+impl<'a, T> Iterator for PreorderWithChildCounts<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // The outermost open node is the earliest one, so the front is
+            // complete unless it's that node.
+            if !self.pending.is_empty() && self.open.first() != Some(&self.yielded) {
+                self.yielded += 1;
+                return self.pending.pop_front();
+            }
+            let (edge, value) = self.cursor.next()?;
+            if is_leading(edge) {
+                if let Some(&parent) = self.open.last() {
+                    self.pending[parent - self.yielded].1 += 1;
+                }
+                self.open.push(self.yielded + self.pending.len());
+                self.pending.push_back((value, 0));
+            } else {
+                self.open.pop();
+            }
         }
     }
 }
 
-impl<'a, T> std::fmt::Debug for Cursor<'a, T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "{:?}", self.cursor)
+struct RootTrees<'a, T: 'a> {
+    cursor: Cursor<'a, T>,
+}
+
+impl<'a, T> Iterator for RootTrees<'a, T> {
+    type Item = Cursor<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor == self.cursor.forest.end() {
+            return None;
+        }
+        let root = Cursor { forest: self.cursor.forest, cursor: self.cursor.cursor };
+        self.cursor.trailing_of();
+        self.cursor.move_next();
+        Some(root)
     }
 }
 
-pub struct CursorMut<'a, T: 'a> {
-    forest: &'a mut Forest<T>,
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CursorRelation {
+    Same,
+    Before,
+    After,
+    Ancestor,
+    Descendant,
+    DifferentTree,
+}
+
+/// Identifies a node without borrowing its forest. Ids are only meaningful
+/// while the node is alive, as a freed node's id may be reused.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    fn of<T>(cursor: &RawCursor<T>) -> Self {
+        NodeId(cursor.node as usize)
+    }
+}
+
+/// A read-only position in a forest. It borrows the forest shared, so no
+/// `CursorMut` can erase its node while it, or a collection holding it, is
+/// alive:
+///
+/// ```compile_fail
+/// use skog::Forest;
+///
+/// let mut forest = Forest::singleton(1);
+/// let cursors = vec![forest.begin()];
+/// forest.begin_mut().erase();
+/// cursors[0].current();
+/// ```
+pub struct Cursor<'a, T: 'a> {
+    forest: &'a Forest<T>,
     cursor: RawCursor<T>,
 }
 
-impl<'a, T> CursorMut<'a, T> {
-    pub fn size(&mut self) -> usize {
-        self.forest.size()
+// Not `Copy`, since a cursor is also an iterator and silently copying one
+// would make it easy to advance a copy instead of the original.
+impl<'a, T> Clone for Cursor<'a, T> {
+    fn clone(&self) -> Self {
+        Cursor { forest: self.forest, cursor: self.cursor }
     }
+}
 
-    pub fn empty(&self) -> bool {
-        self.forest.empty()
+impl<'a, T> PartialEq for Cursor<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cursor == other.cursor
     }
+}
+
+impl<'a, T> Eq for Cursor<'a, T> {}
 
+impl<'a, T> Cursor<'a, T> {
     pub fn leading_of(&mut self) {
         self.cursor = self.cursor.leading_of();
     }
@@ -484,303 +882,4029 @@ impl<'a, T> CursorMut<'a, T> {
         self.cursor.edge
     }
 
-    pub fn current(&mut self) -> Option<&'a mut T> {
+    /// The value of the node at the cursor, or `None` at the root and end.
+    ///
+    /// The reference lives as long as the cursor's shared borrow of the
+    /// forest rather than the borrow of the cursor: moving a `Cursor` never
+    /// changes the forest, and nothing can change it while the reference is
+    /// held.
+    ///
+    /// ```compile_fail
+    /// use skog::Forest;
+    ///
+    /// let mut forest = Forest::singleton(1);
+    /// let value = forest.begin().current().unwrap();
+    /// forest.begin_mut().erase();
+    /// assert_eq!(*value, 1);
+    /// ```
+    pub fn current(&self) -> Option<&'a T> {
         unsafe {
             if self.cursor.equal_node(&self.forest.unsafe_root()) {
                 None
             } else {
-                self.cursor.current_mut()
+                self.cursor.current()
             }
         }
     }
 
-    pub fn insert(&mut self, item: T) {
-        if self.forest.size_valid() {
-            self.forest.size += 1;
+    /// Moves forward `n` nodes in preorder. Stepping past the last node onto
+    /// `end()` counts as a step, like exhausting an iterator does.
+    pub fn advance_by(&mut self, n: usize) -> Result<(), usize> {
+        for step in 0..n {
+            if *self == self.forest.end() {
+                return Err(n - step);
+            }
+            self.move_next();
+            self.find_edge(ForestEdge::Leading);
         }
-        unsafe { self.cursor.insert(item); }
+        Ok(())
     }
 
-    pub fn insert_and_move(&mut self, item: T) {
-        if self.forest.size_valid() {
-            self.forest.size += 1;
+    pub fn next_leaf(&mut self) -> bool {
+        let end = self.forest.end();
+        let mut position = Cursor { forest: self.forest, cursor: self.cursor };
+        while position != end {
+            position.move_next();
+            position.find_edge(ForestEdge::Leading);
+            if position != end && unsafe { !position.cursor.has_children() } {
+                *self = position;
+                return true;
+            }
         }
-        self.cursor = unsafe { self.cursor.insert(item) };
+        false
     }
 
-    pub fn splice(&mut self, mut x: Forest<T>) {
-        if self.forest.size_valid() && x.size_valid() {
-            self.forest.size += x.size();
-        } else {
-            self.forest.size = 0;
+    pub fn prev_leaf(&mut self) -> bool {
+        let root = self.forest.unsafe_root();
+        let mut position = Cursor { forest: self.forest, cursor: self.cursor };
+        while !position.cursor.equal_node(&root) || position.cursor.is_trailing() {
+            position.move_prev();
+            position.find_edge_reverse(ForestEdge::Leading);
+            if !position.cursor.equal_node(&root) && unsafe { !position.cursor.has_children() } {
+                *self = position;
+                return true;
+            }
         }
-        unsafe { self.cursor.splice(x.unsafe_begin(), x.unsafe_end()); }
+        false
     }
 
-    pub fn splice_and_move(&mut self, mut x: Forest<T>) {
-        if self.forest.size_valid() && x.size_valid() {
-            self.forest.size += x.size();
-        } else {
-            self.forest.size = 0;
+    /// The number of ancestors of the node, so top-level nodes (and the root
+    /// itself) are at depth 0.
+    pub fn depth(&self) -> usize {
+        let root = self.forest.unsafe_root();
+        let mut depth = 0;
+        if !self.cursor.equal_node(&root) {
+            let mut position = unsafe { self.cursor.parent() };
+            while !position.equal_node(&root) {
+                depth += 1;
+                position = unsafe { position.parent() };
+            }
         }
-        self.cursor = unsafe { self.cursor.splice(x.unsafe_begin(), x.unsafe_end()) };
+        depth
     }
 
-    #[allow(dead_code)]
-    fn remove(&mut self) {
-        if self.forest.size_valid() {
-            self.forest.size -= 1;
+    pub fn root_of(&self) -> Cursor<'a, T> {
+        let root = self.forest.unsafe_root();
+        let mut position = self.cursor;
+        if !position.equal_node(&root) {
+            position = position.leading_of();
+            loop {
+                let parent = unsafe { position.parent() };
+                if parent.equal_node(&root) {
+                    break;
+                }
+                position = parent.leading_of();
+            }
         }
-        self.cursor = unsafe { self.cursor.erase() };
+        Cursor { forest: self.forest, cursor: position }
     }
-}
 
-impl<'a, T> std::fmt::Debug for CursorMut<'a, T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "{:?}", self.cursor)
+    pub fn same_tree(&self, other: &Cursor<T>) -> bool {
+        std::ptr::eq(self.forest, other.forest)
+            && self.root_of().cursor.equal_node(&other.root_of().cursor)
     }
-}
-
-pub struct Forest<T> {
-    size: usize,
-    tail: *mut Node<T>,
-}
 
-impl<T> Forest<T> {
-    pub fn new() -> Self {
-        unsafe {
-            let this = Self {
-                size: 0,
-                tail: Box::into_raw(Box::new(Node::uninit())),
-            };
-            (*this.tail).base.init(this.tail);
-            this
+    /// How this cursor's node relates to `other`'s, ignoring edges. The
+    /// forest's root is treated as the ancestor of every node.
+    pub fn relation_to(&self, other: &Cursor<T>) -> CursorRelation {
+        use CursorRelation::*;
+        if !std::ptr::eq(self.forest, other.forest) {
+            return DifferentTree;
+        }
+        if self.cursor.equal_node(&other.cursor) {
+            return Same;
         }
+        let root = self.forest.unsafe_root();
+        if self.cursor.equal_node(&root) || self.is_ancestor_of(other) {
+            return Ancestor;
+        }
+        if other.cursor.equal_node(&root) || other.is_ancestor_of(self) {
+            return Descendant;
+        }
+        let tree = self.root_of();
+        if tree.cursor != other.root_of().cursor {
+            return DifferentTree;
+        }
+        let last = tree.cursor.trailing_of();
+        let mut position = self.cursor.leading_of();
+        while position != last {
+            if position.equal_node(&other.cursor) {
+                return Before;
+            }
+            unsafe { position.move_next(); }
+        }
+        After
     }
 
-    pub fn size(&mut self) -> usize {
-        if !self.size_valid() {
-            let c = EdgeCursor::new(ForestEdge::Leading, self.begin());
-            let i = c.into_iter();
-            self.size = i.count();
+    fn is_ancestor_of(&self, other: &Cursor<T>) -> bool {
+        let root = self.forest.unsafe_root();
+        let mut position = other.cursor;
+        while !position.equal_node(&root) {
+            position = unsafe { position.parent() };
+            if position.equal_node(&self.cursor) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The node's 0-based position in preorder, with `end()` at the node
+    /// count. Offsets aren't stable: inserting or removing a node shifts the
+    /// offsets of every node after it.
+    pub fn to_offset(&self) -> usize {
+        let mut offset = 0;
+        let mut position = self.forest.begin();
+        while position != self.forest.end() && !position.cursor.equal_node(&self.cursor) {
+            offset += 1;
+            position.move_next();
+            position.find_edge(ForestEdge::Leading);
+        }
+        offset
+    }
+
+    /// Child axis moves land on the leading edge of the new position and
+    /// return false, leaving the cursor untouched, if there's no such node.
+    pub fn move_to_first_child(&mut self) -> bool {
+        let child = unsafe { self.cursor.leading_of().next() };
+        if child.is_leading() {
+            self.cursor = child;
+        }
+        child.is_leading()
+    }
+
+    pub fn move_to_next_child(&mut self) -> bool {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return false;
+        }
+        match unsafe { self.cursor.next_sibling() } {
+            Some(sibling) => {
+                self.cursor = sibling;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn move_to_prev_child(&mut self) -> bool {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return false;
+        }
+        match unsafe { self.cursor.prev_sibling() } {
+            Some(sibling) => {
+                self.cursor = sibling;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Neither holds for the forest's root, which isn't a node of its own.
+    pub fn has_children(&self) -> bool {
+        !self.cursor.equal_node(&self.forest.unsafe_root()) && unsafe { self.cursor.has_children() }
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        !self.cursor.equal_node(&self.forest.unsafe_root()) && unsafe { !self.cursor.has_children() }
+    }
+
+    /// Moves to the leading edge of the parent, returning false for top-level
+    /// nodes and the root.
+    pub fn to_parent(&mut self) -> bool {
+        let root = self.forest.unsafe_root();
+        if self.cursor.equal_node(&root) {
+            return false;
+        }
+        let parent = unsafe { self.cursor.parent() };
+        if parent.equal_node(&root) {
+            return false;
+        }
+        self.cursor = parent.leading_of();
+        true
+    }
+
+    /// The `[first, last)` range covering the node's subtree, from its leading
+    /// edge to just past its trailing edge. The root's range is the whole
+    /// forest.
+    pub fn subtree_bounds(&self) -> (Cursor<'a, T>, Cursor<'a, T>) {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return (self.forest.begin(), self.forest.end());
+        }
+        let first = self.cursor.leading_of();
+        let last = unsafe { self.cursor.trailing_of().next() };
+        (Cursor { forest: self.forest, cursor: first }, Cursor { forest: self.forest, cursor: last })
+    }
+
+    /// The root's children are the top-level nodes.
+    pub fn children(&self) -> ChildIter<'a, T> {
+        ChildIter {
+            forest: self.forest,
+            position: unsafe { self.cursor.leading_of().next() },
+        }
+    }
+
+    /// The values of the node's later siblings, nearest first.
+    pub fn siblings_after(&self) -> impl Iterator<Item = &'a T> {
+        let forest = self.forest;
+        let mut position = if self.cursor.equal_node(&forest.unsafe_root()) {
+            forest.unsafe_end()
+        } else {
+            unsafe { self.cursor.trailing_of().next() }
+        };
+        std::iter::from_fn(move || {
+            if !position.is_leading() {
+                return None;
+            }
+            let value = Cursor { forest, cursor: position }.current();
+            unsafe { position.move_next_child(); }
+            value
+        })
+    }
+
+    /// The values of the node's earlier siblings, in forest order.
+    pub fn siblings_before(&self) -> impl Iterator<Item = &'a T> {
+        let forest = self.forest;
+        let node = self.cursor.leading_of();
+        let mut position = if node.equal_node(&forest.unsafe_root()) {
+            node
+        } else {
+            unsafe { node.parent().leading_of().next() }
+        };
+        std::iter::from_fn(move || {
+            if position.equal_node(&node) {
+                return None;
+            }
+            let value = Cursor { forest, cursor: position }.current();
+            unsafe { position.move_next_child(); }
+            value
+        })
+    }
+
+    pub fn id(&self) -> NodeId {
+        NodeId::of(&self.cursor)
+    }
+
+    pub fn sibling_index(&self) -> usize {
+        let mut index = 0;
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return index;
+        }
+        let mut position = self.cursor;
+        while let Some(prev) = unsafe { position.prev_sibling() } {
+            position = prev;
+            index += 1;
+        }
+        index
+    }
+
+    fn find_edge(&mut self, edge: ForestEdge) {
+        while self.cursor.edge != edge && *self != self.forest.end() {
+            self.move_next();
+        }
+    }
+
+    fn find_edge_reverse(&mut self, edge: ForestEdge) {
+        while self.cursor.edge != edge {
+            self.move_prev();
+        }
+    }
+}
+
+impl<'a, T: Clone> Cursor<'a, T> {
+    pub fn clone_subtree(&self) -> Forest<T> {
+        let mut forest = Forest::new();
+        let mut cur = forest.end_mut();
+        let root = self.forest.unsafe_root();
+        let last = self.cursor.trailing_of();
+        let mut position = self.cursor.leading_of();
+        while position != last {
+            if !position.equal_node(&root) {
+                if position.is_leading() {
+                    cur.insert_and_move(unsafe { position.current() }.unwrap().clone());
+                    cur.trailing_of();
+                } else {
+                    cur.move_next();
+                }
+            }
+            unsafe { position.move_next(); }
+        }
+        forest
+    }
+}
+
+// Walking a cursor as an iterator yields every edge from its position up to
+// `end()`, advancing the cursor itself. Starting at the root skips its edge.
+impl<'a, T> Iterator for Cursor<'a, T> {
+    type Item = (ForestEdge, &'a T);
+
+    fn next(&mut self) -> Option<(ForestEdge, &'a T)> {
+        if self.cursor == self.forest.unsafe_root() {
+            self.move_next();
+        }
+        if self.cursor == self.forest.unsafe_end() {
+            return None;
+        }
+        let item = (self.edge(), self.current().unwrap());
+        self.move_next();
+        Some(item)
+    }
+}
+
+impl<'a, T> std::fmt::Debug for Cursor<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self.cursor)
+    }
+}
+
+pub struct CursorMut<'a, T: 'a> {
+    forest: &'a mut Forest<T>,
+    cursor: RawCursor<T>,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn size(&mut self) -> usize {
+        self.forest.size()
+    }
+
+    pub fn empty(&self) -> bool {
+        self.forest.empty()
+    }
+
+    pub fn leading_of(&mut self) {
+        self.cursor = self.cursor.leading_of();
+    }
+
+    pub fn trailing_of(&mut self) {
+        self.cursor = self.cursor.trailing_of();
+    }
+
+    pub fn move_next(&mut self) {
+        unsafe { self.cursor.move_next(); }
+    }
+
+    pub fn move_prev(&mut self) {
+        unsafe { self.cursor.move_prev(); }
+    }
+
+    pub fn edge(&self) -> ForestEdge {
+        self.cursor.edge
+    }
+
+    /// The value of the node at the cursor, or `None` at the root and end.
+    /// The reference borrows the cursor, so only one can be held at a time:
+    ///
+    /// ```compile_fail
+    /// use skog::Forest;
+    ///
+    /// let mut forest = Forest::singleton(1);
+    /// let mut cursor = forest.begin_mut();
+    /// let a = cursor.current().unwrap();
+    /// let b = cursor.current().unwrap();
+    /// *a += *b;
+    /// ```
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe {
+            if self.cursor.equal_node(&self.forest.unsafe_root()) {
+                None
+            } else {
+                self.cursor.current_mut()
+            }
+        }
+    }
+
+    pub fn peek_mut(&mut self) -> Option<(ForestEdge, &mut T)> {
+        let edge = self.edge();
+        self.current().map(|value| (edge, value))
+    }
+
+    pub fn to_parent(&mut self) -> bool {
+        let root = self.forest.unsafe_root();
+        if self.cursor.equal_node(&root) {
+            return false;
+        }
+        let parent = unsafe { self.cursor.parent() };
+        if parent.equal_node(&root) {
+            return false;
+        }
+        self.cursor = parent.leading_of();
+        true
+    }
+
+    pub fn has_children(&self) -> bool {
+        !self.cursor.equal_node(&self.forest.unsafe_root()) && unsafe { self.cursor.has_children() }
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        !self.cursor.equal_node(&self.forest.unsafe_root()) && unsafe { !self.cursor.has_children() }
+    }
+
+    /// Moves to the leading edge of the next or previous sibling, skipping
+    /// over its subtree, and returns false if there's none.
+    pub fn move_to_next_child(&mut self) -> bool {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return false;
+        }
+        match unsafe { self.cursor.next_sibling() } {
+            Some(sibling) => {
+                self.cursor = sibling;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn move_to_prev_child(&mut self) -> bool {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return false;
+        }
+        match unsafe { self.cursor.prev_sibling() } {
+            Some(sibling) => {
+                self.cursor = sibling;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// From the root this maps every node, as the root's subtree is the whole
+    /// forest. At `end()` nothing is mapped.
+    pub fn map_subtree_in_place<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        if self.cursor == self.forest.unsafe_end() {
+            return;
+        }
+        let root = self.forest.unsafe_root();
+        let last = self.cursor.trailing_of();
+        let mut position = self.cursor.leading_of();
+        while position != last {
+            // The sentinel root has no data, its "subtree" is the whole forest.
+            if position.is_leading() && !position.equal_node(&root) {
+                unsafe { position.current_mut().map(&mut f); }
+            }
+            unsafe { position.move_next(); }
+        }
+    }
+
+    /// Rotations treat the first two children of a node as its left and right
+    /// subtree, a lone child being the left one. A rotation whose result
+    /// couldn't be expressed that way is refused and `false` is returned. On
+    /// success the cursor is left on the new subtree root.
+    pub fn rotate_left(&mut self) -> bool {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return false;
+        }
+        unsafe {
+            let x = self.cursor.leading_of();
+            let l = x.next();
+            if l.is_trailing() {
+                return false;
+            }
+            let r = l.trailing_of().next();
+            if r.is_trailing() {
+                return false;
+            }
+            x.splice_subtree(r);
+            let rl = r.next();
+            if rl.is_leading() {
+                l.trailing_of().next().splice_subtree(rl);
+            }
+            r.next().splice_subtree(x);
+            self.cursor = r;
+        }
+        self.forest.validate_if_enabled();
+        true
+    }
+
+    pub fn rotate_right(&mut self) -> bool {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return false;
+        }
+        unsafe {
+            let x = self.cursor.leading_of();
+            let l = x.next();
+            if l.is_trailing() {
+                return false;
+            }
+            let ll = l.next();
+            if ll.is_trailing() {
+                return false;
+            }
+            let has_right = l.trailing_of().next().is_leading();
+            let lr = ll.trailing_of().next();
+            if has_right && lr.is_trailing() {
+                return false;
+            }
+            x.splice_subtree(l);
+            if lr.is_leading() {
+                x.next().splice_subtree(lr);
+            }
+            ll.trailing_of().next().splice_subtree(x);
+            self.cursor = l;
+        }
+        self.forest.validate_if_enabled();
+        true
+    }
+
+    /// On the root this splits the top-level trees. `end()` has no children,
+    /// so an empty forest is returned there.
+    pub fn split_children_at(&mut self, index: usize) -> Forest<T> {
+        let mut split = Forest::new();
+        if self.cursor == self.forest.unsafe_end() {
+            return split;
+        }
+        unsafe {
+            let last = self.cursor.trailing_of();
+            let mut first = self.cursor.leading_of().next();
+            for _ in 0..index {
+                if first == last {
+                    break;
+                }
+                first.move_next_child();
+            }
+            let mut position = first;
+            while position != last {
+                split.grow_size(position.subtree_size());
+                position.move_next_child();
+            }
+            split.unsafe_end().splice(first, last);
+        }
+        self.forest.shrink_size(split.size());
+        self.forest.validate_if_enabled();
+        split
+    }
+
+    /// Moves the node and its descendants into a forest of their own, leaving
+    /// the cursor on the edge that followed the subtree. Like `erase`, this
+    /// does nothing on the root and `end()` and returns an empty forest.
+    pub fn detach_subtree(&mut self) -> Forest<T> {
+        let mut detached = Forest::new();
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return detached;
+        }
+        unsafe {
+            let last = self.cursor.trailing_of().next();
+            detached.grow_size(self.cursor.subtree_size());
+            detached.unsafe_end().splice(self.cursor.leading_of(), last);
+            self.cursor = last;
+        }
+        self.forest.shrink_size(detached.size());
+        self.forest.validate_if_enabled();
+        detached
+    }
+
+    /// Removes the node and every later sibling along with their subtrees,
+    /// leaving the cursor on the parent's trailing edge (`end()` for
+    /// top-level nodes).
+    pub fn truncate_level(&mut self) {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return;
+        }
+        let mut removed = 0;
+        unsafe {
+            let first = self.cursor.leading_of();
+            let mut last = first;
+            while last.is_leading() {
+                removed += last.subtree_size();
+                last.move_next_child();
+            }
+            self.cursor = first.erase_range(last);
+        }
+        self.forest.shrink_size(removed);
+        self.forest.validate_if_enabled();
+    }
+
+    /// Inserts a node before the cursor and moves up to `count` of the
+    /// following sibling subtrees, starting with the one at the cursor, under
+    /// it. The cursor is left on the new node's leading edge. At the root the
+    /// new node wraps the first top-level trees instead.
+    pub fn insert_parent(&mut self, item: T, count: usize) {
+        if self.cursor == self.forest.unsafe_root() {
+            self.cursor = self.forest.unsafe_begin();
+        }
+        self.forest.grow_size(1);
+        unsafe {
+            let parent = self.cursor.insert(item);
+            let mut trailing = parent.trailing_of();
+            let first = trailing.next();
+            let mut last = first;
+            for _ in 0..count {
+                if !last.is_leading() {
+                    break;
+                }
+                last.move_next_child();
+            }
+            trailing.splice(first, last);
+            self.cursor = parent;
+        }
+        self.forest.validate_if_enabled();
+    }
+
+    /// Inserts a single node just before the cursor, which is all `splice`
+    /// would do with a one-node forest. On a leading edge the node becomes the
+    /// previous sibling of the cursor's node; on a trailing edge it becomes
+    /// the node's last child.
+    pub fn insert(&mut self, item: T) {
+        self.forest.grow_size(1);
+        unsafe { self.cursor.insert(item); }
+        self.forest.validate_if_enabled();
+    }
+
+    pub fn insert_and_move(&mut self, item: T) {
+        self.forest.grow_size(1);
+        self.cursor = unsafe { self.cursor.insert(item) };
+        self.forest.validate_if_enabled();
+    }
+
+    pub fn splice(&mut self, x: Forest<T>) {
+        match x.size {
+            Some(size) => self.forest.grow_size(size),
+            None => self.forest.invalidate_size(),
+        }
+        unsafe { self.cursor.splice(x.unsafe_begin(), x.unsafe_end()); }
+        self.forest.validate_if_enabled();
+    }
+
+    pub fn splice_and_move(&mut self, x: Forest<T>) {
+        match x.size {
+            Some(size) => self.forest.grow_size(size),
+            None => self.forest.invalidate_size(),
+        }
+        self.cursor = unsafe { self.cursor.splice(x.unsafe_begin(), x.unsafe_end()) };
+        self.forest.validate_if_enabled();
+    }
+
+    /// Erases the current node, leaving the cursor on the edge that followed
+    /// the erased one: the first promoted child or next sibling when on a
+    /// leading edge, the edge after the node's trailing edge otherwise.
+    ///
+    /// Only this node is freed. Its children are promoted into its place, so
+    /// the forest's size always drops by one. Does nothing on the forest's
+    /// root or `end()`.
+    pub fn erase(&mut self) {
+        if self.cursor.equal_node(&self.forest.unsafe_root()) {
+            return;
+        }
+        self.forest.shrink_size(1);
+        self.cursor = unsafe { self.cursor.erase() };
+        self.forest.validate_if_enabled();
+    }
+}
+
+impl<'a, T> std::fmt::Debug for CursorMut<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self.cursor)
+    }
+}
+
+pub struct Forest<T> {
+    // The cached node count, `None` once it can no longer be trusted.
+    size: Option<usize>,
+    tail: *mut Node<T>,
+}
+
+// The forest exclusively owns every node reachable from `tail`, and the raw
+// pointers are never shared with another forest, so sending or sharing it is
+// as safe as sending or sharing the values themselves.
+unsafe impl<T: Send> Send for Forest<T> {}
+unsafe impl<T: Sync> Sync for Forest<T> {}
+
+impl<T> Forest<T> {
+    pub fn new() -> Self {
+        unsafe {
+            let this = Self {
+                size: Some(0),
+                tail: Box::into_raw(Box::new(Node::uninit())),
+            };
+            (*this.tail).base.init(this.tail);
+            this
+        }
+    }
+
+    /// Creates a forest holding a single root node. To add children to it,
+    /// take `begin_mut()` and move it to the root's trailing edge with
+    /// `trailing_of()`.
+    pub fn singleton(value: T) -> Self {
+        let mut forest = Forest::new();
+        forest.end_mut().insert(value);
+        forest
+    }
+
+    pub fn size(&mut self) -> usize {
+        match self.size {
+            Some(size) => size,
+            None => {
+                let c = EdgeCursor::new(ForestEdge::Leading, self.begin());
+                let size = c.into_iter().count();
+                self.size = Some(size);
+                size
+            }
+        }
+    }
+
+    /// The node count without updating the cache, counting the nodes when the
+    /// cached size isn't trusted.
+    pub fn len(&self) -> usize {
+        match self.size {
+            Some(size) => size,
+            None => self.edges(ForestEdge::Leading).count(),
         }
-        self.size
     }
 
     pub fn size_valid(&self) -> bool {
-        self.size != 0 || self.empty()
+        self.size_is_cached()
+    }
+
+    pub fn size_is_cached(&self) -> bool {
+        self.size.is_some()
+    }
+
+    /// Drops the cached size so that the next `size()` recounts the nodes.
+    pub fn invalidate_size(&mut self) {
+        self.size = None;
+    }
+
+    pub fn empty(&self) -> bool {
+        self.begin() == self.end()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.empty()
+    }
+
+    // The cached size is only adjusted while it's trusted, and any arithmetic
+    // inconsistency drops it so that the next `size()` recounts instead of
+    // panicking or wrapping.
+    fn grow_size(&mut self, n: usize) {
+        self.size = self.size.and_then(|size| size.checked_add(n));
+    }
+
+    fn shrink_size(&mut self, n: usize) {
+        self.size = self.size.and_then(|size| size.checked_sub(n));
+    }
+
+    pub fn edges(&self, edge: ForestEdge) -> EdgeIter<'_, T> {
+        EdgeIter {
+            front: EdgeCursor::new(edge, self.begin()),
+            back: EdgeCursor::new(edge, self.end()),
+            remaining: self.size,
+        }
+    }
+
+    /// Counts the nodes up front if the cached size isn't trusted, so that the
+    /// iterator always knows its length.
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut inner = self.edges(ForestEdge::Leading);
+        inner.remaining = Some(self.len());
+        Iter { inner }
+    }
+
+    pub fn preorder(&self) -> Iter<'_, T> {
+        self.iter()
+    }
+
+    /// Walks back from `end()` over leading edges, stopping at `begin()`.
+    pub fn rev_preorder(&self) -> std::iter::Rev<Iter<'_, T>> {
+        self.iter().rev()
+    }
+
+    /// Named apart from `edges`, which yields only the edges of one kind.
+    pub fn fullorder(&self) -> Edges<'_, T> {
+        Edges { cursor: self.begin() }
+    }
+
+    pub fn postorder(&self) -> Postorder<'_, T> {
+        Postorder { inner: self.edges(ForestEdge::Trailing) }
+    }
+
+    /// A forest of the same shape holding `f` of every value.
+    pub fn map<U, F: FnMut(&T) -> U>(&self, mut f: F) -> Forest<U> {
+        let mut forest = Forest::new();
+        let mut cur = forest.end_mut();
+        for (edge, value) in self.begin() {
+            if is_leading(edge) {
+                cur.insert_and_move(f(value));
+                cur.trailing_of();
+            } else {
+                cur.move_next();
+            }
+        }
+        forest
+    }
+
+    pub fn breadth_first(&self) -> Bfs<'_, T> {
+        let mut queue = std::collections::VecDeque::new();
+        if !self.is_empty() {
+            queue.push_back(self.unsafe_begin());
+        }
+        Bfs { forest: self, queue }
+    }
+
+    pub fn filter<F: Fn(&T) -> bool>(&self, pred: F) -> Filter<'_, T, F> {
+        Filter { cursor: self.begin(), pred }
+    }
+
+    pub fn preorder_filtered<F: FnMut(&T) -> Keep>(&self, f: F) -> PreorderFiltered<'_, T, F> {
+        PreorderFiltered { cursor: self.begin(), f }
+    }
+
+    pub fn extract_where<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> Forest<T> {
+        let mut extracted = Forest::new();
+        let end = self.unsafe_end();
+        let mut position = self.unsafe_begin();
+        unsafe {
+            while position != end {
+                if position.is_leading() && pred(position.current().unwrap()) {
+                    let last = position.trailing_of().next();
+                    extracted.grow_size(position.subtree_size());
+                    extracted.unsafe_end().splice(position, last);
+                    position = last;
+                } else {
+                    position.move_next();
+                }
+            }
+        }
+        self.shrink_size(extracted.size());
+        self.validate_if_enabled();
+        extracted
+    }
+
+    /// The pattern's top-level trees are matched as an ordered subsequence of
+    /// some sibling group in `self`, so a single tree pattern matches a subtree
+    /// rooted at any node.
+    pub fn contains_shape<P>(&self, pattern: &Forest<P>, matches: impl Fn(&T, &P) -> bool) -> bool {
+        let end = self.unsafe_end();
+        let mut position = self.unsafe_root();
+        unsafe {
+            loop {
+                if embeds(position, pattern.unsafe_root(), &matches) {
+                    return true;
+                }
+                position.move_next();
+                while position.is_trailing() && position != end {
+                    position.move_next();
+                }
+                if position == end {
+                    return false;
+                }
+            }
+        }
+    }
+
+    pub fn preorder_with_child_counts(&self) -> impl Iterator<Item = (&T, usize)> {
+        PreorderWithChildCounts {
+            cursor: self.begin(),
+            pending: Default::default(),
+            open: Vec::new(),
+            yielded: 0,
+        }
+    }
+
+    pub fn root_trees(&self) -> impl Iterator<Item = Cursor<'_, T>> {
+        RootTrees { cursor: self.begin() }
+    }
+
+    pub fn swap(&mut self, other: &mut Forest<T>) {
+        std::mem::swap(&mut self.tail, &mut other.tail);
+        std::mem::swap(&mut self.size, &mut other.size);
+    }
+
+    /// Moves all of `other`'s trees after this forest's last top-level tree.
+    pub fn append(&mut self, other: Forest<T>) {
+        self.end_mut().splice(other);
+    }
+
+    pub fn interleave(&mut self, other: Forest<T>) {
+        match other.size {
+            Some(size) => self.grow_size(size),
+            None => self.invalidate_size(),
+        }
+        let end = self.unsafe_end();
+        let other_end = other.unsafe_end();
+        let mut position = self.unsafe_begin();
+        unsafe {
+            loop {
+                let first = other.unsafe_begin();
+                if first == other_end {
+                    break;
+                }
+                if position != end {
+                    position = position.trailing_of().next();
+                }
+                position.splice_subtree(first);
+            }
+        }
+        self.validate_if_enabled();
+    }
+
+    pub fn root_count(&self) -> usize {
+        self.root_trees().count()
+    }
+
+    pub fn map_roots<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        let end = self.unsafe_end();
+        let mut cur = self.begin_mut();
+        while cur.cursor != end {
+            cur.current().map(&mut f);
+            cur.trailing_of();
+            cur.move_next();
+        }
+    }
+
+    /// The new node is appended after the last top-level tree, and the matched
+    /// trees become its children in their original order.
+    pub fn group_under<F: FnMut(&T) -> bool>(&mut self, parent_value: T, mut pred: F) {
+        let matched: Vec<_> = self.root_trees()
+            .filter(|tree| pred(tree.current().unwrap()))
+            .map(|tree| tree.cursor)
+            .collect();
+        self.grow_size(1);
+        unsafe {
+            let parent = self.unsafe_end().insert(parent_value);
+            for tree in matched {
+                parent.trailing_of().splice_subtree(tree);
+            }
+        }
+        self.validate_if_enabled();
+    }
+
+    pub fn assign_depths<F: FnMut(&mut T, usize)>(&mut self, mut f: F) {
+        let end = self.unsafe_end();
+        let mut position = self.unsafe_begin();
+        let mut tracker = DepthTracker::default();
+        unsafe {
+            while position != end {
+                let depth = tracker.visit(position.edge).unwrap();
+                if position.is_leading() {
+                    f(position.current_mut().unwrap(), depth);
+                }
+                position.move_next();
+            }
+        }
+    }
+
+    /// The path's first index selects a top-level tree and every following
+    /// index a child of the node selected so far. Grafted trees are appended
+    /// after the node's existing children.
+    pub fn graft_at_path(&mut self, path: &[usize], subtree: Forest<T>) -> Result<(), PathError> {
+        let node = self.raw_at_path(path)?;
+        match subtree.size {
+            Some(size) => self.grow_size(size),
+            None => self.invalidate_size(),
+        }
+        unsafe { node.trailing_of().splice(subtree.unsafe_begin(), subtree.unsafe_end()); }
+        self.validate_if_enabled();
+        Ok(())
+    }
+
+    /// Appends `child`'s trees after the existing children of the node with
+    /// id `at`, taken from a `Cursor` before borrowing the forest mutably. If
+    /// no node of this forest has that id, `child` is handed back untouched.
+    pub fn graft(&mut self, at: NodeId, child: Forest<T>) -> Result<(), Forest<T>> {
+        let end = self.unsafe_end();
+        let mut node = self.unsafe_begin();
+        while NodeId::of(&node) != at {
+            if node == end {
+                return Err(child);
+            }
+            unsafe { node.move_next(); }
+        }
+        match child.size {
+            Some(size) => self.grow_size(size),
+            None => self.invalidate_size(),
+        }
+        unsafe { node.trailing_of().splice(child.unsafe_begin(), child.unsafe_end()); }
+        self.validate_if_enabled();
+        Ok(())
+    }
+
+    fn raw_at_path(&self, path: &[usize]) -> Result<RawCursor<T>, PathError> {
+        let mut node = self.unsafe_root();
+        for (index, &child_index) in path.iter().enumerate() {
+            unsafe {
+                let mut child = node.leading_of().next();
+                for _ in 0..child_index {
+                    if child.is_trailing() {
+                        break;
+                    }
+                    child.move_next_child();
+                }
+                if child.is_trailing() {
+                    return Err(PathError { index });
+                }
+                node = child;
+            }
+        }
+        Ok(node)
+    }
+
+    pub fn collapse_chains<F: FnMut(&T, &T) -> T>(&mut self, mut combine: F) {
+        let end = self.unsafe_end();
+        let mut position = self.unsafe_begin();
+        unsafe {
+            while position != end {
+                if position.is_leading() {
+                    while position.child_count() == 1 {
+                        let child = position.next();
+                        let combined = combine(position.current().unwrap(), child.current().unwrap());
+                        *position.current_mut().unwrap() = combined;
+                        // The child's own children are promoted into its place.
+                        child.erase();
+                        self.shrink_size(1);
+                    }
+                }
+                position.move_next();
+            }
+        }
+        self.validate_if_enabled();
+    }
+
+    /// Folds every subtree bottom-up, handing `f` each node's value and its
+    /// children's results in order, and keeps every node's result.
+    pub fn memoized_fold<A: Clone, F: FnMut(&T, &[A]) -> A>(&self, mut f: F) -> std::collections::HashMap<NodeId, A> {
+        let mut results = std::collections::HashMap::new();
+        let mut children: Vec<Vec<A>> = vec![Vec::new()];
+        let mut cur = self.begin();
+        while cur != self.end() {
+            if is_leading(cur.edge()) {
+                children.push(Vec::new());
+            } else {
+                let folded = f(cur.current().unwrap(), &children.pop().unwrap());
+                results.insert(cur.id(), folded.clone());
+                children.last_mut().unwrap().push(folded);
+            }
+            cur.move_next();
+        }
+        results
+    }
+
+    /// A single walk running `down` on every node in preorder and `up` in
+    /// postorder with the results of the node's children, both threading the
+    /// same state. Returns the final state and the results of the top-level
+    /// trees.
+    pub fn scan_fold<S, A, FDown, FUp>(&self, init: S, mut down: FDown, mut up: FUp) -> (S, Vec<A>)
+    where
+        FDown: FnMut(&mut S, &T),
+        FUp: FnMut(&mut S, &T, Vec<A>) -> A,
+    {
+        let mut state = init;
+        let mut children: Vec<Vec<A>> = vec![Vec::new()];
+        let mut cur = self.begin();
+        while cur != self.end() {
+            let value = cur.current().unwrap();
+            if is_leading(cur.edge()) {
+                down(&mut state, value);
+                children.push(Vec::new());
+            } else {
+                let folded = up(&mut state, value, children.pop().unwrap());
+                children.last_mut().unwrap().push(folded);
+            }
+            cur.move_next();
+        }
+        (state, children.pop().unwrap())
+    }
+
+    /// The node with the most children, the first in preorder on ties.
+    pub fn widest_node(&self) -> Option<Cursor<'_, T>> {
+        let mut widest: Option<(Cursor<T>, usize)> = None;
+        let mut cur = self.begin();
+        while cur != self.end() {
+            let children = unsafe { cur.cursor.child_count() };
+            if widest.as_ref().is_none_or(|(_, most)| children > *most) {
+                widest = Some((Cursor { forest: self, cursor: cur.cursor }, children));
+            }
+            cur.move_next();
+            cur.find_edge(ForestEdge::Leading);
+        }
+        widest.map(|(cursor, _)| cursor)
+    }
+
+    pub fn cursor_at_offset(&self, n: usize) -> Option<Cursor<'_, T>> {
+        let mut cursor = self.begin();
+        match cursor.advance_by(n) {
+            Ok(()) if cursor != self.end() => Some(cursor),
+            _ => None,
+        }
+    }
+
+    pub fn count_where<F: FnMut(&T) -> bool>(&self, mut pred: F) -> usize {
+        self.edges(ForestEdge::Leading).filter(|value| pred(value)).count()
+    }
+
+    /// Top-level nodes are at depth 0.
+    pub fn count_at_depth(&self, depth: usize) -> usize {
+        let mut count = 0;
+        let mut tracker = DepthTracker::default();
+        for (edge, _) in self.begin() {
+            if tracker.visit(edge).unwrap() == depth && is_leading(edge) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// The number of levels in the forest: 0 when empty, 1 when there are only
+    /// top-level nodes and one more for each level of nesting below them.
+    /// Unlike `depth` and `count_at_depth` this counts levels rather than
+    /// indexing them, so it's one more than the deepest node's depth.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut tracker = DepthTracker::default();
+        for (edge, _) in self.begin() {
+            let depth = tracker.visit(edge).unwrap();
+            if is_leading(edge) {
+                height = height.max(depth + 1);
+            }
+        }
+        height
+    }
+
+    /// The leading edge of the first node in preorder matching `pred`.
+    pub fn find<F: FnMut(&T) -> bool>(&self, mut pred: F) -> Option<Cursor<'_, T>> {
+        let mut cur = self.begin();
+        while let Some(value) = cur.current() {
+            if pred(value) {
+                return Some(cur);
+            }
+            cur.move_next();
+            cur.find_edge(ForestEdge::Leading);
+        }
+        None
+    }
+
+    pub fn find_all<F: FnMut(&T) -> bool>(&self, mut pred: F) -> Vec<Cursor<'_, T>> {
+        let mut found = Vec::new();
+        let mut cur = self.begin();
+        while let Some(value) = cur.current() {
+            if pred(value) {
+                found.push(Cursor { forest: self, cursor: cur.cursor });
+            }
+            cur.move_next();
+            cur.find_edge(ForestEdge::Leading);
+        }
+        found
+    }
+
+    /// Removes the subtrees rooted at the given nodes, returning how many
+    /// nodes were freed. A node inside an already removed subtree is simply
+    /// gone with it, and ids of nodes not in this forest are ignored.
+    pub fn remove_all(&mut self, nodes: &[NodeId]) -> usize {
+        let targets: std::collections::HashSet<_> = nodes.iter().copied().collect();
+        let end = self.unsafe_end();
+        let mut position = self.unsafe_begin();
+        let mut removed = 0;
+        unsafe {
+            while position != end {
+                if position.is_leading() && targets.contains(&NodeId::of(&position)) {
+                    let last = position.trailing_of().next();
+                    removed += position.subtree_size();
+                    position = position.erase_range(last);
+                } else {
+                    position.move_next();
+                }
+            }
+        }
+        self.shrink_size(removed);
+        self.validate_if_enabled();
+        removed
+    }
+
+    /// Removes every node failing `pred` together with its whole subtree, so
+    /// descendants of a removed node are dropped without being tested.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut pred: F) {
+        let end = self.unsafe_end();
+        let mut position = self.unsafe_begin();
+        let mut removed = 0;
+        unsafe {
+            while position != end {
+                if position.is_leading() && !pred(position.current().unwrap()) {
+                    let last = position.trailing_of().next();
+                    removed += position.subtree_size();
+                    position = position.erase_range(last);
+                } else {
+                    position.move_next();
+                }
+            }
+        }
+        self.shrink_size(removed);
+        self.validate_if_enabled();
+    }
+
+    /// Reverses the siblings at every level by relinking the nodes; no values
+    /// are moved and the size is unchanged.
+    pub fn reverse(&mut self) {
+        let mut nodes = vec![self.unsafe_root()];
+        let mut cur = self.begin();
+        while cur != self.end() {
+            nodes.push(cur.cursor);
+            cur.move_next();
+            cur.find_edge(ForestEdge::Leading);
+        }
+        for node in nodes {
+            unsafe { node.reverse_children(); }
+        }
+        self.validate_if_enabled();
+    }
+
+    pub fn into_postorder(self) -> impl Iterator<Item = T> {
+        let cursor = self.unsafe_begin();
+        IntoPostorder { forest: self, cursor }
+    }
+
+    pub fn into_depth_pairs(self) -> impl Iterator<Item = (usize, T)> {
+        IntoDepthPairs { forest: self, remaining: Vec::new() }
+    }
+
+    // Builds a forest from preorder `(depth, value)` pairs, where each depth
+    // may be at most one deeper than the one before it.
+    fn from_depth_pairs<I>(pairs: I) -> Result<Self, DepthError>
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let mut forest = Forest::new();
+        let mut cur = forest.end_mut();
+        let mut open = 0;
+        for (index, (depth, value)) in pairs.into_iter().enumerate() {
+            if depth > open {
+                return Err(DepthError { index, depth });
+            }
+            // Close the previous node and the ancestors we're leaving.
+            for _ in depth..open {
+                cur.move_next();
+            }
+            cur.insert_and_move(value);
+            cur.trailing_of();
+            open = depth + 1;
+        }
+        Ok(forest)
+    }
+
+    /// Rebuilds a forest from owned fullorder `(edge, value)` pairs. Only the
+    /// values on leading edges are kept; those on trailing edges just close the
+    /// innermost open node and are dropped.
+    pub fn from_edge_stream<I>(iter: I) -> Result<Self, EdgeStreamError>
+    where
+        I: IntoIterator<Item = (ForestEdge, T)>,
+    {
+        let mut forest = Forest::new();
+        let mut cur = forest.end_mut();
+        let mut open = 0;
+        let mut index = 0;
+        for (edge, value) in iter {
+            match edge {
+                ForestEdge::Leading => {
+                    cur.insert_and_move(value);
+                    cur.trailing_of();
+                    open += 1;
+                }
+                ForestEdge::Trailing => {
+                    if open == 0 {
+                        return Err(EdgeStreamError { index });
+                    }
+                    cur.move_next();
+                    open -= 1;
+                }
+            }
+            index += 1;
+        }
+        if open > 0 {
+            return Err(EdgeStreamError { index });
+        }
+        Ok(forest)
+    }
+
+    /// Top-level nodes are at depth 0.
+    pub fn flatten(self) -> Vec<(usize, T)> {
+        self.into_depth_pairs().collect()
+    }
+
+    /// The inverse of `flatten`. Unlike `try_from`, a depth that skips levels
+    /// isn't rejected but clamped to one below the previous node.
+    pub fn unflatten(items: Vec<(usize, T)>) -> Forest<T> {
+        let mut open = 0;
+        let clamped = items.into_iter().map(|(depth, value)| {
+            let depth = depth.min(open);
+            open = depth + 1;
+            (depth, value)
+        });
+        Forest::from_depth_pairs(clamped).expect("clamped depths never skip a level")
+    }
+
+    pub fn is_single_tree(&self) -> bool {
+        let begin = self.unsafe_begin();
+        let end = self.unsafe_end();
+        begin != end && unsafe { begin.trailing_of().next() } == end
+    }
+
+    /// The encoding is a little endian u64 node count followed by each node in
+    /// preorder as a little endian i32 depth delta from the previous node and
+    /// the value as written by `write_value`.
+    pub fn to_bytes<W: io::Write>(
+        &self,
+        w: &mut W,
+        write_value: impl Fn(&T, &mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let count = match self.size {
+            Some(size) => size,
+            None => self.edges(ForestEdge::Leading).count(),
+        };
+        w.write_all(&(count as u64).to_le_bytes())?;
+        let mut tracker = DepthTracker::default();
+        let mut prev_depth = 0;
+        let mut cur = self.begin();
+        while cur != self.end() {
+            let depth = tracker.visit(cur.edge())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            if is_leading(cur.edge()) {
+                let delta = i32::try_from(depth as i64 - prev_depth as i64)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                w.write_all(&delta.to_le_bytes())?;
+                write_value(cur.current().unwrap(), w)?;
+                prev_depth = depth;
+            }
+            cur.move_next();
+        }
+        Ok(())
+    }
+
+    pub fn from_bytes<R: io::Read>(
+        r: &mut R,
+        read_value: impl Fn(&mut R) -> io::Result<T>,
+    ) -> io::Result<Self> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut count = [0; 8];
+        r.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+        let mut pairs = Vec::new();
+        let mut depth = 0usize;
+        for _ in 0..count {
+            let mut delta = [0; 4];
+            r.read_exact(&mut delta)?;
+            let delta = i32::from_le_bytes(delta);
+            depth = depth.checked_add_signed(delta as isize)
+                .ok_or_else(|| invalid(format!("depth delta {} underflows", delta)))?;
+            pairs.push((depth, read_value(r)?));
+        }
+        Forest::from_depth_pairs(pairs).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Panics if the forest's links are corrupted, in debug builds only.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(e) = self.validate() {
+                panic!("corrupted forest: {}", e);
+            }
+        }
+    }
+
+    // Called at the end of every method that relinks nodes. Validating is
+    // O(n), so it only happens with the `validate` feature enabled.
+    fn validate_if_enabled(&self) {
+        if cfg!(feature = "validate") {
+            self.debug_assert_valid();
+        }
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        let invalid = |reason| Err(ValidationError { reason });
+        let end = self.unsafe_end();
+        let mut visited = std::collections::HashSet::new();
+        let mut open = Vec::new();
+        let mut position = self.unsafe_root();
+        unsafe {
+            while position != end {
+                if !visited.insert((position.node, position.edge)) {
+                    return invalid("links form a cycle");
+                }
+                let next = position.next();
+                if next.prev() != position {
+                    return invalid("next and prior links disagree");
+                }
+                position = next;
+                if position == end {
+                    break;
+                }
+                if position.is_leading() {
+                    open.push(position.node);
+                } else if open.pop() != Some(position.node) {
+                    return invalid("trailing edge doesn't match the open leading edge");
+                }
+            }
+        }
+        if !open.is_empty() {
+            return invalid("leading edge without a trailing edge");
+        }
+        if self.size.is_some_and(|size| size != visited.len() / 2) {
+            return invalid("cached size is out of date");
+        }
+        Ok(())
+    }
+
+    pub fn build<F: FnOnce(&mut ForestBuilder<T>)>(f: F) -> Self {
+        let mut forest = Forest::new();
+        f(&mut ForestBuilder { cursor: forest.end_mut() });
+        forest
+    }
+
+    /// Builds a forest through a cursor starting at `end()` and validates
+    /// its structure before returning it. A forest failing validation is
+    /// leaked rather than dropped, since freeing it would follow its
+    /// corrupted links.
+    pub fn build_validated<F, E>(f: F) -> Result<Self, E>
+    where
+        F: FnOnce(&mut CursorMut<T>) -> Result<(), E>,
+        E: From<ValidationError>,
+    {
+        let mut forest = Forest::new();
+        f(&mut forest.end_mut())?;
+        if let Err(e) = forest.validate() {
+            std::mem::forget(forest);
+            return Err(e.into());
+        }
+        Ok(forest)
+    }
+
+    pub fn root(&self) -> Cursor<'_, T> {
+        Cursor { forest: self, cursor: self.unsafe_root() }
+    }
+
+    pub fn root_mut(&mut self) -> CursorMut<'_, T> {
+        let cursor = self.unsafe_root();
+        CursorMut { forest: self, cursor }
+    }
+
+    pub fn begin(&self) -> Cursor<'_, T> {
+        Cursor { forest: self, cursor: self.unsafe_begin() }
+    }
+
+    pub fn begin_mut(&mut self) -> CursorMut<'_, T> {
+        let mut c = self.root_mut();
+        c.move_next();
+        c
+    }
+
+    pub fn end(&self) -> Cursor<'_, T> {
+        Cursor { forest: self, cursor: self.unsafe_end() }
+    }
+
+    pub fn end_mut(&mut self) -> CursorMut<'_, T> {
+        let cursor = self.unsafe_end();
+        CursorMut { forest: self, cursor }
+    }
+
+    pub fn clear(&mut self) {
+        let mut position = self.unsafe_begin();
+        let tail = self.tail_mut();
+        // Detach all nodes up front, so that the forest is already empty if
+        // dropping a value panics and `Drop` clears it a second time. The
+        // detached nodes still link to each other and are freed in postorder,
+        // each after moving past its trailing edge.
+        unsafe {
+            (*tail).base.init(tail);
+            self.size = Some(0);
+            while position.node != tail {
+                let next = position.next();
+                if position.is_trailing() {
+                    let node = Box::from_raw(position.node);
+                    drop(node.data.assume_init());
+                }
+                position = next;
+            }
+        }
+    }
+
+    fn unsafe_root(&self) -> RawCursor<T> {
+        RawCursor {
+            node: self.tail_mut(),
+            edge: ForestEdge::Leading,
+        }
+    }
+
+    fn unsafe_begin(&self) -> RawCursor<T> {
+        unsafe {
+            let mut c = self.unsafe_root();
+            c.move_next();
+            c
+        }
+    }
+
+    fn unsafe_end(&self) -> RawCursor<T> {
+        RawCursor::new(self.tail_mut(), ForestEdge::Trailing)
+    }
+
+    fn tail_mut(&self) -> *mut Node<T> {
+        self.tail
+    }
+}
+
+struct IntoPostorder<T> {
+    forest: Forest<T>,
+    cursor: RawCursor<T>,
+}
+
+impl<T> Iterator for IntoPostorder<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        unsafe {
+            while self.cursor.is_leading() {
+                self.cursor.move_next();
+            }
+            if self.cursor == self.forest.unsafe_end() {
+                return None;
+            }
+            // All children have already been yielded, so the node is a leaf
+            // and extracting it leaves the remaining forest intact.
+            let (next, value) = self.cursor.extract();
+            self.forest.shrink_size(1);
+            self.cursor = next;
+            Some(value)
+        }
+    }
+}
+
+/// Moves the values out of a forest in preorder. Nodes that are never
+/// yielded are dropped with the forest when the iterator is.
+pub struct IntoIter<T> {
+    forest: Forest<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // Everything before the next node in preorder has already been
+        // extracted, so it's always found at `begin`.
+        let position = self.forest.unsafe_begin();
+        if position == self.forest.unsafe_end() {
+            return None;
+        }
+        let (_, value) = unsafe { position.extract() };
+        self.forest.shrink_size(1);
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.forest.size {
+            Some(size) => (size, Some(size)),
+            None => (0, None),
+        }
+    }
+}
+
+struct IntoDepthPairs<T> {
+    forest: Forest<T>,
+    // Number of not yet yielded children of each open ancestor.
+    remaining: Vec<usize>,
+}
+
+impl<T> Iterator for IntoDepthPairs<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        // Everything before the next node in preorder has already been
+        // extracted, so it's always found at `begin`.
+        let position = self.forest.unsafe_begin();
+        if position == self.forest.unsafe_end() {
+            return None;
+        }
+        while self.remaining.last() == Some(&0) {
+            self.remaining.pop();
+        }
+        let depth = self.remaining.len();
+        if let Some(remaining) = self.remaining.last_mut() {
+            *remaining -= 1;
+        }
+        unsafe {
+            let children = position.child_count();
+            // Extracting a node promotes its children into its place.
+            let (_, value) = position.extract();
+            self.forest.shrink_size(1);
+            if children > 0 {
+                self.remaining.push(children);
+            }
+            Some((depth, value))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DepthError {
+    pub index: usize,
+    pub depth: usize,
+}
+
+impl std::fmt::Display for DepthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "depth {} at index {} skips a level", self.depth, self.index)
+    }
+}
+
+impl std::error::Error for DepthError {}
+
+/// The element of a child index path that couldn't be resolved.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PathError {
+    pub index: usize,
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "child index at path position {} is out of range", self.index)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// The position of an unmatched trailing edge, or the stream length if some
+/// leading edges were never closed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EdgeStreamError {
+    pub index: usize,
+}
+
+impl std::fmt::Display for EdgeStreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "unbalanced edge at stream position {}", self.index)
+    }
+}
+
+impl std::error::Error for EdgeStreamError {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError {
+    reason: &'static str,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl<T> IntoIterator for Forest<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { forest: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Forest<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+// Collecting values makes each of them a top-level node, in order.
+impl<T> FromIterator<T> for Forest<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut forest = Forest::new();
+        forest.extend(iter);
+        forest
+    }
+}
+
+impl<T> Extend<T> for Forest<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut cur = self.end_mut();
+        for item in iter {
+            cur.insert(item);
+        }
+    }
+}
+
+impl<T> TryFrom<Vec<(usize, T)>> for Forest<T> {
+    type Error = DepthError;
+
+    fn try_from(pairs: Vec<(usize, T)>) -> Result<Self, Self::Error> {
+        Forest::from_depth_pairs(pairs)
+    }
+}
+
+impl<T: Clone> Clone for Forest<T> {
+    fn clone(&self) -> Self {
+        self.root().clone_subtree()
+    }
+}
+
+impl<T: Clone> Forest<T> {
+    /// Collecting from `edges` already reserves the cached size up front.
+    pub fn to_vec_preorder(&self) -> Vec<T> {
+        self.edges(ForestEdge::Leading).cloned().collect()
+    }
+
+    pub fn to_vec_postorder(&self) -> Vec<T> {
+        self.edges(ForestEdge::Trailing).cloned().collect()
+    }
+}
+
+// Forests are equal when they have the same shape and equal values at every
+// position, regardless of their cached sizes.
+impl<T: PartialEq> PartialEq for Forest<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.first_divergence(other).is_none()
+    }
+}
+
+impl<T: Eq> Eq for Forest<T> {}
+
+impl<T: PartialEq> Forest<T> {
+    pub fn contains(&self, x: &T) -> bool {
+        self.find(|value| value == x).is_some()
+    }
+
+    pub fn first_divergence<'a>(&'a self, other: &'a Forest<T>) -> Option<(Cursor<'a, T>, Cursor<'a, T>)> {
+        let mut a = self.begin();
+        let mut b = other.begin();
+        loop {
+            let a_end = a == self.end();
+            let b_end = b == other.end();
+            if a_end && b_end {
+                return None;
+            }
+            if a_end || b_end || a.edge() != b.edge() || a.current() != b.current() {
+                return Some((a, b));
+            }
+            a.move_next();
+            b.move_next();
+        }
+    }
+}
+
+// Each node on its own line, indented one level deeper than its parent.
+impl<T: std::fmt::Debug> std::fmt::Debug for Forest<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.empty() {
+            return write!(f, "Forest []");
+        }
+        writeln!(f, "Forest [")?;
+        let mut tracker = DepthTracker::default();
+        let mut cur = self.begin();
+        while cur != self.end() {
+            let depth = tracker.visit(cur.edge()).map_err(|_| std::fmt::Error)?;
+            if is_leading(cur.edge()) {
+                writeln!(f, "{:indent$}{:?}", "", cur.current().unwrap(), indent = 4 * (depth + 1))?;
+            }
+            cur.move_next();
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T: std::fmt::Display> Forest<T> {
+    /// One line per node in preorder, drawn like the output of `tree`.
+    /// Top-level nodes are printed without a connector.
+    pub fn to_tree_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        // Whether each open ancestor below the top level is a last child.
+        let mut last_flags: Vec<bool> = Vec::new();
+        let mut tracker = DepthTracker::default();
+        let mut cur = self.begin();
+        while cur != self.end() {
+            let depth = tracker.visit(cur.edge()).unwrap();
+            if is_leading(cur.edge()) {
+                let mut line = String::new();
+                if depth > 0 {
+                    let is_last = unsafe { cur.cursor.trailing_of().next().is_trailing() };
+                    for &ancestor_last in &last_flags {
+                        line.push_str(if ancestor_last { "    " } else { "\u{2502}   " });
+                    }
+                    line.push_str(if is_last { "\u{2514}\u{2500}\u{2500} " } else { "\u{251c}\u{2500}\u{2500} " });
+                    last_flags.push(is_last);
+                }
+                line.push_str(&cur.current().unwrap().to_string());
+                lines.push(line);
+            } else if depth > 0 {
+                last_flags.pop();
+            }
+            cur.move_next();
+        }
+        lines
+    }
+}
+
+#[cfg(feature = "graph")]
+impl<T> Forest<T> {
+    /// Node values in preorder together with parent to child edges indexing
+    /// into them, e.g. for building a `petgraph::Graph`.
+    pub fn to_graph_parts(&self) -> (Vec<&T>, Vec<(usize, usize)>) {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut parents = Vec::new();
+        let mut cur = self.begin();
+        while cur != self.end() {
+            if is_leading(cur.edge()) {
+                let index = nodes.len();
+                nodes.push(cur.current().unwrap());
+                if let Some(&parent) = parents.last() {
+                    edges.push((parent, index));
+                }
+                parents.push(index);
+            } else {
+                parents.pop();
+            }
+            cur.move_next();
+        }
+        (nodes, edges)
+    }
+}
+
+/// Appends nodes for [`Forest::build`], each after the ones added before it
+/// at the same level:
+///
+/// ```
+/// use skog::Forest;
+///
+/// let forest = Forest::build(|b| {
+///     b.node("src", |b| {
+///         b.leaf("lib.rs");
+///     });
+///     b.leaf("Cargo.toml");
+/// });
+/// assert_eq!(forest.flatten(), [(0, "src"), (1, "lib.rs"), (0, "Cargo.toml")]);
+/// ```
+pub struct ForestBuilder<'a, T> {
+    cursor: CursorMut<'a, T>,
+}
+
+impl<'a, T> ForestBuilder<'a, T> {
+    /// Adds a node whose children are the ones `children` adds.
+    pub fn node<F: FnOnce(&mut Self)>(&mut self, value: T, children: F) -> &mut Self {
+        self.cursor.insert_and_move(value);
+        self.cursor.trailing_of();
+        children(self);
+        self.cursor.move_next();
+        self
+    }
+
+    pub fn leaf(&mut self, value: T) -> &mut Self {
+        self.cursor.insert(value);
+        self
+    }
+}
+
+impl<T: std::hash::Hash + Eq> Forest<T> {
+    /// Groups of two or more nodes whose subtrees are equal in both shape and
+    /// values, each group and the groups themselves in preorder.
+    pub fn find_duplicate_subtrees(&self) -> Vec<Vec<Cursor<'_, T>>> {
+        let hashes = self.subtree_hashes();
+        let mut buckets: std::collections::HashMap<u64, Vec<Vec<usize>>> = Default::default();
+        let mut groups = Vec::new();
+        for (index, &(node, hash)) in hashes.iter().enumerate() {
+            let bucket = buckets.entry(hash).or_default();
+            let group = bucket
+                .iter_mut()
+                .find(|group| unsafe { subtree_eq(hashes[group[0]].0, node) });
+            match group {
+                Some(group) => group.push(index),
+                None => bucket.push(vec![index]),
+            }
+        }
+        for group in buckets.into_values().flatten() {
+            if group.len() > 1 {
+                groups.push(group);
+            }
+        }
+        groups.sort_unstable_by_key(|group| group[0]);
+        groups
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|index| Cursor { forest: self, cursor: hashes[index].0 })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Every node in preorder with a hash of its subtree, computed bottom-up
+    // in a single pass.
+    fn subtree_hashes(&self) -> Vec<(RawCursor<T>, u64)> {
+        use std::hash::{Hash, Hasher};
+        let mut hashes = Vec::new();
+        let mut open: Vec<(usize, Vec<u64>)> = Vec::new();
+        let mut cur = self.begin();
+        while cur != self.end() {
+            if is_leading(cur.edge()) {
+                open.push((hashes.len(), Vec::new()));
+                hashes.push((cur.cursor, 0));
+            } else {
+                let (index, children) = open.pop().unwrap();
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                cur.current().unwrap().hash(&mut hasher);
+                children.hash(&mut hasher);
+                let hash = hasher.finish();
+                hashes[index].1 = hash;
+                if let Some((_, siblings)) = open.last_mut() {
+                    siblings.push(hash);
+                }
+            }
+            cur.move_next();
+        }
+        hashes
+    }
+}
+
+// Whether the subtrees rooted at `a` and `b` are equal in shape and values.
+unsafe fn subtree_eq<T: PartialEq>(a: RawCursor<T>, b: RawCursor<T>) -> bool {
+    let a_last = a.trailing_of();
+    let b_last = b.trailing_of();
+    let mut a = a.leading_of();
+    let mut b = b.leading_of();
+    while a != a_last {
+        if b == b_last || a.edge != b.edge || a.current() != b.current() {
+            return false;
+        }
+        a.move_next();
+        b.move_next();
+    }
+    b == b_last
+}
+
+impl<T> Default for Forest<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Forest<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(f: &Forest<(char, std::rc::Rc<()>)>) {
+        struct Tabs(usize);
+
+        impl std::fmt::Display for Tabs {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+                for _ in 0..self.0 {
+                    write!(f, "\t")?;
+                }
+                Ok(())
+            }
+        }
+
+        let mut depths = DepthTracker::default();
+        for (edge, (value, _)) in f.begin() {
+            let depth = depths.visit(edge).unwrap();
+            match edge {
+                ForestEdge::Leading => println!("{}<{}>", Tabs(depth), value),
+                ForestEdge::Trailing => println!("{}</{}>", Tabs(depth), value),
+            }
+        }
+    }
+
+    // Builds the same A(B(C(F, G, H), D(I, J, K), E)) shape as `big_test_forest`.
+    fn big_forest_with<T>(mut make: impl FnMut(char) -> T) -> Forest<T> {
+        let mut f = Forest::new();
+        let mut cur = f.end_mut();
+        cur.insert_and_move(make('A'));
+        cur.trailing_of();
+        cur.insert_and_move(make('B'));
+        cur.trailing_of();
+        for (parent, children) in [('C', "FGH"), ('D', "IJK"), ('E', "")] {
+            cur.insert_and_move(make(parent));
+            cur.trailing_of();
+            for c in children.chars() {
+                cur.insert(make(c));
+            }
+            cur.move_next();
+        }
+        f
+    }
+
+    fn big_forest() -> Forest<char> {
+        big_forest_with(|c| c)
+    }
+
+    // Cursor on the leading edge of the first node with the given value.
+    fn find<T: PartialEq>(f: &Forest<T>, value: T) -> Cursor<'_, T> {
+        let mut cur = f.begin();
+        while cur.current() != Some(&value) {
+            cur.move_next();
+        }
+        cur
+    }
+
+    // Collects the full edge sequence of a forest.
+    fn fullorder<T: Clone>(f: &Forest<T>) -> Vec<(ForestEdge, T)> {
+        let mut result = Vec::new();
+        let mut cur = f.begin();
+        while cur != f.end() {
+            result.push((cur.edge(), cur.current().unwrap().clone()));
+            cur.move_next();
+        }
+        result
+    }
+
+    // Collects the values of a forest in preorder.
+    fn preorder<T: Clone>(f: &Forest<T>) -> Vec<T> {
+        fullorder(f).into_iter().filter(|(e, _)| is_leading(*e)).map(|(_, v)| v).collect()
+    }
+
+    #[test]
+    fn big_test_forest() {
+        let mut data = std::rc::Rc::new(());
+
+        let mut c = Forest::new();
+        let mut cur = c.end_mut();
+        cur.insert_and_move(('C', data.clone()));
+        cur.trailing_of();
+
+        cur.insert(('F', data.clone()));
+        cur.insert(('G', data.clone()));
+        cur.insert(('H', data.clone()));
+
+        let mut d = Forest::new();
+        let mut cur = d.end_mut();
+        cur.insert_and_move(('D', data.clone()));
+        cur.trailing_of();
+
+        cur.insert(('I', data.clone()));
+        cur.insert(('J', data.clone()));
+        cur.insert(('K', data.clone()));
+
+        let mut e = Forest::new();
+        let mut cur = e.end_mut();
+        cur.insert_and_move(('E', data.clone()));
+
+        let mut b = Forest::new();
+        let mut cur = b.end_mut();
+        cur.insert_and_move(('B', data.clone()));
+        cur.trailing_of();
+
+        cur.splice(c);
+        cur.splice(d);
+        cur.splice(e);
+
+        let mut a = Forest::new();
+        let mut cur = a.end_mut();
+        cur.insert_and_move(('A', data.clone()));
+        cur.trailing_of();
+
+        cur.splice(b);
+
+        print(&a);
+
+        assert_eq!(a.size(), 11);
+
+        let mut cur = a.begin();
+        assert_eq!(cur.current().map(|(c, _)| c), Some(&'A'));
+
+        cur.move_next();
+        /**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'B'));
+
+        cur.move_next();
+        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'C'));
+
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'F'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'F'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'G'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'G'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'H'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'H'));
+
+        cur.move_next();
+        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'C'));
+
+        cur.move_next();
+        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'D'));
+
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'I'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'I'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'J'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'J'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'K'));
+        cur.move_next();
+        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'K'));
+
+        cur.move_next();
+        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'D'));
+
+        cur.move_next();
+        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'E'));
+        cur.move_next();
+        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'E'));
+
+        cur.move_next();
+        /**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'B'));
+
+        cur.move_next();
+        assert_eq!(cur.current().map(|(c, _)| c), Some(&'A'));
+
+        cur.move_next();
+        assert_eq!(cur, a.end());
+
+        a.clear();
+        assert!(a.empty());
+
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    }
+
+    #[test]
+    fn map_subtree_in_place() {
+        let mut f = big_forest_with(|c| c.to_ascii_lowercase());
+        let mut cur = f.begin_mut();
+        cur.move_next();
+        cur.move_next();
+        assert_eq!(cur.current(), Some(&mut 'c'));
+
+        cur.map_subtree_in_place(|c| *c = c.to_ascii_uppercase());
+        assert_eq!(preorder(&f), "abCFGHdijke".chars().collect::<Vec<_>>());
+
+        // Mapping from `end()` does nothing, from the root covers the whole
+        // forest.
+        f.end_mut().map_subtree_in_place(|c| *c = c.to_ascii_uppercase());
+        assert_eq!(preorder(&f), "abCFGHdijke".chars().collect::<Vec<_>>());
+        f.root_mut().map_subtree_in_place(|c| *c = c.to_ascii_uppercase());
+        assert_eq!(preorder(&f), "ABCFGHDIJKE".chars().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn size_survives_interleaved_splice_and_remove() {
+        fn count<T: Clone>(f: &Forest<T>) -> usize {
+            preorder(f).len()
+        }
+
+        let mut f = Forest::new();
+        for i in 0..8 {
+            let mut g = Forest::new();
+            let mut cur = g.end_mut();
+            cur.insert_and_move(i);
+            cur.trailing_of();
+            cur.insert(i * 10);
+            cur.insert(i * 10 + 1);
+
+            let mut cur = f.end_mut();
+            cur.splice(g);
+            assert_eq!(f.size(), count(&f));
+
+            let mut cur = f.begin_mut();
+            if i % 2 == 0 {
+                cur.erase();
+            }
+            assert_eq!(f.size(), count(&f));
+        }
+
+        // An out of sync cache is dropped rather than underflowing.
+        f.size = Some(1);
+        f.shrink_size(2);
+        assert!(!f.size_is_cached());
+        assert_eq!(f.size(), count(&f));
+    }
+
+    #[test]
+    fn edges() {
+        let f = big_forest();
+        let preorder: String = f.edges(ForestEdge::Leading).collect();
+        assert_eq!(preorder, "ABCFGHDIJKE");
+        let postorder: String = f.edges(ForestEdge::Trailing).collect();
+        assert_eq!(postorder, "FGHCIJKDEBA");
+
+        let empty = Forest::<char>::new();
+        assert_eq!(empty.edges(ForestEdge::Leading).next(), None);
+        assert_eq!(empty.edges(ForestEdge::Trailing).next(), None);
+    }
+
+    #[test]
+    fn into_postorder() {
+        let f = big_forest();
+        let postorder: String = f.into_postorder().collect();
+        assert_eq!(postorder, "FGHCIJKDEBA");
+
+        let mut data = std::rc::Rc::new(());
+        let f = big_forest_with(|c| (c, data.clone()));
+        let mut iter = f.into_postorder();
+        assert_eq!(iter.next().map(|(c, _)| c), Some('F'));
+        assert_eq!(iter.next().map(|(c, _)| c), Some('G'));
+        assert_eq!(std::rc::Rc::strong_count(&data), 10);
+        drop(iter);
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    }
+
+    #[test]
+    fn sibling_index() {
+        let f = big_forest();
+        assert_eq!(find(&f, 'A').sibling_index(), 0);
+        assert_eq!(find(&f, 'C').sibling_index(), 0);
+        assert_eq!(find(&f, 'D').sibling_index(), 1);
+        assert_eq!(find(&f, 'E').sibling_index(), 2);
+        assert_eq!(find(&f, 'K').sibling_index(), 2);
+
+        let mut d = find(&f, 'D');
+        d.trailing_of();
+        assert_eq!(d.sibling_index(), 1);
+        assert_eq!(f.end().sibling_index(), 0);
+    }
+
+    #[test]
+    fn into_depth_pairs() {
+        let pairs: Vec<_> = big_forest().into_depth_pairs().collect();
+        assert_eq!(pairs[..4], [(0, 'A'), (1, 'B'), (2, 'C'), (3, 'F')]);
+        assert_eq!(pairs[6..], [(2, 'D'), (3, 'I'), (3, 'J'), (3, 'K'), (2, 'E')]);
+
+        let mut f = Forest::try_from(pairs).unwrap();
+        assert_eq!(f.size(), 11);
+        assert_eq!(fullorder(&f), fullorder(&big_forest()));
+
+        assert_eq!(
+            Forest::try_from(vec![(0, 'A'), (2, 'B')]).err(),
+            Some(DepthError { index: 1, depth: 2 }),
+        );
+
+        let mut data = std::rc::Rc::new(());
+        let f = big_forest_with(|c| (c, data.clone()));
+        let mut iter = f.into_depth_pairs();
+        iter.next();
+        iter.next();
+        drop(iter);
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    }
+
+    #[test]
+    fn first_divergence() {
+        let f = big_forest();
+        assert!(f.first_divergence(&big_forest()).is_none());
+
+        // Appending a root diverges where the original ends.
+        let mut g = big_forest();
+        g.end_mut().insert('Z');
+        let (a, b) = f.first_divergence(&g).unwrap();
+        assert_eq!(a, f.end());
+        assert_eq!((b.edge(), b.current()), (ForestEdge::Leading, Some(&'Z')));
+
+        // Appending a child under E diverges at E's trailing edge.
+        let mut g = big_forest();
+        let mut cur = g.begin_mut();
+        while cur.current() != Some(&mut 'E') {
+            cur.move_next();
+        }
+        cur.trailing_of();
+        cur.insert('Z');
+        let (a, b) = f.first_divergence(&g).unwrap();
+        assert_eq!((a.edge(), a.current()), (ForestEdge::Trailing, Some(&'E')));
+        assert_eq!((b.edge(), b.current()), (ForestEdge::Leading, Some(&'Z')));
+    }
+
+    #[test]
+    fn preorder_filtered() {
+        let f = big_forest();
+        let visited: String = f
+            .preorder_filtered(|c| match c {
+                'C' => Keep::SkipSubtree,
+                'D' => Keep::Skip,
+                _ => Keep::Yield,
+            })
+            .collect();
+        assert_eq!(visited, "ABIJKE");
+
+        let visited: String = f.preorder_filtered(|_| Keep::SkipSubtree).collect();
+        assert_eq!(visited, "");
+    }
+
+    #[test]
+    fn singleton() {
+        let mut f = Forest::singleton('A');
+        assert_eq!(f.size(), 1);
+        assert_eq!(f.edges(ForestEdge::Leading).collect::<Vec<_>>(), [&'A']);
+
+        let mut cur = f.begin_mut();
+        cur.trailing_of();
+        cur.insert('B');
+        assert_eq!(fullorder(&f), [
+            (ForestEdge::Leading, 'A'),
+            (ForestEdge::Leading, 'B'),
+            (ForestEdge::Trailing, 'B'),
+            (ForestEdge::Trailing, 'A'),
+        ]);
+    }
+
+    #[test]
+    fn extract_where() {
+        let is_vowel = |c: &char| "AEIOU".contains(*c);
+
+        let mut f = big_forest();
+        let mut extracted = f.extract_where(|c| c != &'A' && is_vowel(c));
+        assert_eq!(preorder(&f), "ABCFGHDJK".chars().collect::<Vec<_>>());
+        assert_eq!(preorder(&extracted), ['I', 'E']);
+        assert_eq!((f.size(), extracted.size()), (9, 2));
+
+        // Matched subtrees move as a whole and aren't searched further.
+        let mut f = big_forest();
+        let mut extracted = f.extract_where(is_vowel);
+        assert!(f.empty());
+        assert_eq!(f.size(), 0);
+        assert_eq!(extracted.size(), 11);
+        assert_eq!(fullorder(&extracted), fullorder(&big_forest()));
+    }
+
+    #[test]
+    fn is_single_tree() {
+        assert!(big_forest().is_single_tree());
+        assert!(!Forest::<char>::new().is_single_tree());
+
+        let mut f = big_forest();
+        f.end_mut().insert('Z');
+        assert!(!f.is_single_tree());
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn to_graph_parts() {
+        let f = big_forest();
+        let (nodes, edges) = f.to_graph_parts();
+        assert_eq!(nodes.into_iter().collect::<String>(), "ABCFGHDIJKE");
+        assert_eq!(edges, [
+            (0, 1), (1, 2), (2, 3), (2, 4), (2, 5), (1, 6), (6, 7), (6, 8), (6, 9), (1, 10),
+        ]);
+    }
+
+    #[test]
+    fn advance_by() {
+        let f = big_forest();
+        let mut cur = f.begin();
+        assert_eq!(cur.advance_by(4), Ok(()));
+        assert_eq!(cur.current(), Some(&'G'));
+        assert_eq!(cur.advance_by(1), Ok(()));
+        assert_eq!(cur.current(), Some(&'H'));
+        assert_eq!(cur.advance_by(0), Ok(()));
+        assert_eq!(cur.current(), Some(&'H'));
+        // Skips the trailing edges of H and C.
+        assert_eq!(cur.advance_by(1), Ok(()));
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&'D')));
+
+        let mut cur = f.begin();
+        assert_eq!(cur.advance_by(100), Err(89));
+        assert_eq!(cur, f.end());
+        let mut cur = f.begin();
+        assert_eq!(cur.advance_by(11), Ok(()));
+        assert_eq!(cur, f.end());
+    }
+
+    #[test]
+    fn contains_shape() {
+        fn pattern(pairs: &[(usize, char)]) -> Forest<char> {
+            Forest::try_from(pairs.to_vec()).unwrap()
+        }
+
+        let f = big_forest();
+        let eq = |a: &char, b: &char| a == b;
+        assert!(f.contains_shape(&pattern(&[(0, 'B'), (1, 'D')]), eq));
+        assert!(f.contains_shape(&pattern(&[(0, 'C'), (1, 'F'), (1, 'H')]), eq));
+        assert!(f.contains_shape(&pattern(&[(0, 'B'), (1, 'C'), (2, 'G'), (1, 'E')]), eq));
+        assert!(f.contains_shape(&pattern(&[(0, 'C'), (0, 'E')]), eq));
+        assert!(f.contains_shape(&pattern(&[]), eq));
+
+        assert!(!f.contains_shape(&pattern(&[(0, 'D'), (1, 'F')]), eq));
+        assert!(!f.contains_shape(&pattern(&[(0, 'C'), (1, 'H'), (1, 'F')]), eq));
+        assert!(!f.contains_shape(&pattern(&[(0, 'E'), (0, 'C')]), eq));
+        assert!(!f.contains_shape(&pattern(&[(0, 'A'), (1, 'C')]), eq));
+
+        let any_vowel_parent = Forest::try_from(vec![(0, "vowel"), (1, "any")]).unwrap();
+        assert!(f.contains_shape(&any_vowel_parent, |c, p| *p == "any" || "AEIOU".contains(*c)));
+        assert!(!f.contains_shape(&any_vowel_parent, |c, p| *p == "any" || "EIOU".contains(*c)));
+    }
+
+    #[test]
+    fn preorder_with_child_counts() {
+        let f = big_forest();
+        let counts: Vec<_> = f.preorder_with_child_counts().map(|(c, n)| (*c, n)).collect();
+        assert_eq!(counts, [
+            ('A', 1), ('B', 3), ('C', 3), ('F', 0), ('G', 0), ('H', 0),
+            ('D', 3), ('I', 0), ('J', 0), ('K', 0), ('E', 0),
+        ]);
+
+        let f = Forest::try_from(vec![(0, 'a'), (0, 'b'), (1, 'c'), (1, 'd'), (2, 'e'), (0, 'f')]).unwrap();
+        let counts: Vec<_> = f.preorder_with_child_counts().map(|(c, n)| (*c, n)).collect();
+        assert_eq!(counts, [('a', 0), ('b', 2), ('c', 0), ('d', 1), ('e', 0), ('f', 0)]);
+        assert_eq!(Forest::<char>::new().preorder_with_child_counts().next(), None);
+    }
+
+    #[test]
+    fn next_and_prev_leaf() {
+        let f = big_forest();
+        let mut cur = find(&f, 'F');
+        assert!(cur.next_leaf());
+        assert_eq!(cur.current(), Some(&'G'));
+        assert!(cur.next_leaf());
+        assert_eq!(cur.current(), Some(&'H'));
+        assert!(cur.next_leaf());
+        assert_eq!(cur.current(), Some(&'I'));
+        assert!(cur.prev_leaf());
+        assert_eq!(cur.current(), Some(&'H'));
+
+        let mut cur = find(&f, 'E');
+        assert!(!cur.next_leaf());
+        assert_eq!(cur.current(), Some(&'E'));
+        assert!(cur.prev_leaf());
+        assert_eq!(cur.current(), Some(&'K'));
+
+        let mut cur = find(&f, 'F');
+        assert!(!cur.prev_leaf());
+        assert_eq!(cur.current(), Some(&'F'));
+
+        let mut cur = f.begin();
+        assert!(cur.next_leaf());
+        assert_eq!(cur.current(), Some(&'F'));
+        let mut cur = f.end();
+        assert!(cur.prev_leaf());
+        assert_eq!(cur.current(), Some(&'E'));
+        assert!(!f.end().next_leaf());
+    }
+
+    #[test]
+    fn rotate() {
+        unsafe fn in_order(node: RawCursor<i32>, out: &mut Vec<i32>) {
+            let left = node.leading_of().next();
+            let right = left.trailing_of().next();
+            if left.is_leading() {
+                in_order(left, out);
+            }
+            out.push(*node.current().unwrap());
+            if left.is_leading() && right.is_leading() {
+                in_order(right, out);
+            }
+        }
+
+        fn in_order_of(f: &Forest<i32>) -> Vec<i32> {
+            let mut out = Vec::new();
+            unsafe { in_order(f.unsafe_begin(), &mut out); }
+            out
+        }
+
+        let bst = || Forest::try_from(vec![
+            (0, 4), (1, 2), (2, 1), (2, 3), (1, 6), (2, 5), (2, 7),
+        ]).unwrap();
+
+        let mut f = bst();
+        let mut cur = f.begin_mut();
+        assert!(cur.rotate_left());
+        assert_eq!(cur.current(), Some(&mut 6));
+        assert_eq!(preorder(&f), [6, 4, 2, 1, 3, 5, 7]);
+        assert_eq!(in_order_of(&f), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(f.size(), 7);
+
+        let mut cur = f.begin_mut();
+        assert!(cur.rotate_right());
+        assert_eq!(fullorder(&f), fullorder(&bst()));
+
+        let mut f = bst();
+        assert!(f.begin_mut().rotate_right());
+        assert_eq!(preorder(&f), [2, 1, 4, 3, 6, 5, 7]);
+        assert_eq!(in_order_of(&f), [1, 2, 3, 4, 5, 6, 7]);
+
+        // Missing children.
+        let mut f = Forest::try_from(vec![(0, 4), (1, 2), (2, 1)]).unwrap();
+        assert!(!f.begin_mut().rotate_left());
+        assert!(f.begin_mut().rotate_right());
+        assert_eq!(preorder(&f), [2, 1, 4]);
+        assert_eq!(in_order_of(&f), [1, 2, 4]);
+        assert!(f.begin_mut().rotate_left());
+        assert_eq!(preorder(&f), [4, 2, 1]);
+
+        let mut f = Forest::try_from(vec![(0, 4), (1, 2), (2, 1), (1, 6)]).unwrap();
+        assert!(!f.begin_mut().rotate_right());
+        assert!(!f.root_mut().rotate_left());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let write_char = |c: &char, w: &mut Vec<u8>| w.write_all(&u32::from(*c).to_le_bytes());
+        let read_char = |r: &mut &[u8]| {
+            let mut c = [0; 4];
+            r.read_exact(&mut c)?;
+            char::from_u32(u32::from_le_bytes(c))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid char"))
+        };
+
+        use std::io::{Read, Write};
+        let mut bytes = Vec::new();
+        big_forest().to_bytes(&mut bytes, write_char).unwrap();
+        assert_eq!(bytes.len(), 8 + 11 * (4 + 4));
+        assert_eq!(bytes[..8], 11u64.to_le_bytes());
+
+        let mut f = Forest::from_bytes(&mut &bytes[..], read_char).unwrap();
+        assert_eq!(f.size(), 11);
+        assert_eq!(fullorder(&f), fullorder(&big_forest()));
+
+        let mut bytes = Vec::new();
+        Forest::new().to_bytes(&mut bytes, write_char).unwrap();
+        assert!(Forest::from_bytes(&mut &bytes[..], read_char).unwrap().empty());
+
+        // Truncated input and skipped levels are rejected.
+        let mut bytes = Vec::new();
+        big_forest().to_bytes(&mut bytes, write_char).unwrap();
+        let err = Forest::from_bytes(&mut &bytes[..bytes.len() - 1], read_char).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        bytes[16..20].copy_from_slice(&2i32.to_le_bytes());
+        let err = Forest::from_bytes(&mut &bytes[..], read_char).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn root_of() {
+        let mut f = big_forest();
+        f.end_mut().insert('Z');
+        let a = find(&f, 'A');
+        assert_eq!(find(&f, 'H').root_of(), a);
+        assert_eq!(find(&f, 'E').root_of(), a);
+        assert_eq!(a.root_of(), a);
+        let mut b = find(&f, 'B');
+        b.trailing_of();
+        assert_eq!(b.root_of(), a);
+        assert_eq!(find(&f, 'Z').root_of(), find(&f, 'Z'));
+        assert_eq!(f.end().root_of(), f.end());
+    }
+
+    #[test]
+    fn root_trees() {
+        let mut f = big_forest();
+        let mut cur = f.end_mut();
+        cur.insert('Y');
+        cur.insert('Z');
+        let roots: Vec<_> = f.root_trees().collect();
+        assert_eq!(roots.len(), 3);
+        assert_eq!(roots[0], find(&f, 'A'));
+        assert_eq!(roots[1], find(&f, 'Y'));
+        assert_eq!(roots[2], find(&f, 'Z'));
+
+        assert_eq!(Forest::<char>::new().root_trees().count(), 0);
+    }
+
+    #[test]
+    fn depth_tracker() {
+        use ForestEdge::*;
+        let mut tracker = DepthTracker::default();
+        assert_eq!(tracker.visit(Leading), Ok(0));
+        assert_eq!(tracker.visit(Leading), Ok(1));
+        assert_eq!(tracker.visit(Trailing), Ok(1));
+        assert_eq!(tracker.visit(Trailing), Ok(0));
+        assert_eq!(tracker.visit(Trailing), Err(UnbalancedEdge));
+        assert_eq!(tracker.visit(Leading), Ok(0));
+    }
+
+    #[test]
+    fn clone_subtree() {
+        let data = std::rc::Rc::new(());
+        let f = big_forest_with(|c| (c, data.clone()));
+        let mut c = f.begin();
+        c.advance_by(2).unwrap();
+        let mut clone = c.clone_subtree();
+        assert_eq!(clone.size(), 4);
+        assert_eq!(preorder(&clone).into_iter().map(|(c, _)| c).collect::<String>(), "CFGH");
+        assert!(clone.is_single_tree());
+        assert_eq!(std::rc::Rc::strong_count(&data), 1 + 11 + 4);
+
+        drop(clone);
+        assert_eq!(std::rc::Rc::strong_count(&data), 1 + 11);
+        assert_eq!(preorder(&f).len(), 11);
+
+        let mut leaf = find(&f, ('K', data.clone())).clone_subtree();
+        assert_eq!(leaf.size(), 1);
+        assert_eq!(fullorder(&f.root().clone_subtree()), fullorder(&f));
+    }
+
+    #[test]
+    fn interleave() {
+        let mut f: Forest<char> = Forest::try_from(vec![(0, 'A'), (0, 'C')]).unwrap();
+        f.interleave(Forest::try_from(vec![(0, 'B'), (0, 'D')]).unwrap());
+        assert_eq!(preorder(&f), ['A', 'B', 'C', 'D']);
+        assert_eq!(f.size(), 4);
+
+        let mut f = Forest::try_from(vec![(0, 'A'), (1, 'x'), (0, 'C')]).unwrap();
+        f.interleave(Forest::try_from(vec![(0, 'B'), (1, 'y'), (0, 'D'), (0, 'E')]).unwrap());
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [
+            (0, 'A'), (1, 'x'), (0, 'B'), (1, 'y'), (0, 'C'), (0, 'D'), (0, 'E'),
+        ]);
+
+        let mut f = Forest::try_from(vec![(0, 'A'), (0, 'C'), (0, 'E')]).unwrap();
+        f.interleave(Forest::try_from(vec![(0, 'B')]).unwrap());
+        assert_eq!(preorder(&f), ['A', 'B', 'C', 'E']);
+        assert_eq!(f.size(), 4);
+
+        let mut f = Forest::new();
+        f.interleave(Forest::try_from(vec![(0, 'A'), (0, 'B')]).unwrap());
+        assert_eq!(preorder(&f), ['A', 'B']);
+    }
+
+    #[test]
+    fn debug_assert_valid() {
+        let f = big_forest();
+        assert_eq!(f.validate(), Ok(()));
+        f.debug_assert_valid();
+        Forest::<char>::new().debug_assert_valid();
+
+        // Make F's trailing edge skip over G.
+        let f = big_forest();
+        let g = find(&f, 'G').cursor.node;
+        let h = find(&f, 'H').cursor.node;
+        let f_node = find(&f, 'F').cursor.node;
+        unsafe { (*f_node).base.trailing_next = h; }
+        assert!(f.validate().is_err());
+        if cfg!(debug_assertions) {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f.debug_assert_valid()));
+            assert!(result.is_err());
+        }
+        unsafe { (*f_node).base.trailing_next = g; }
+        f.debug_assert_valid();
+
+        let mut f = big_forest();
+        f.size = Some(3);
+        assert_eq!(f.validate().unwrap_err().to_string(), "cached size is out of date");
+    }
+
+    #[test]
+    fn root_count() {
+        let mut f = big_forest();
+        assert_eq!(f.root_count(), 1);
+        let mut cur = f.end_mut();
+        cur.insert('Y');
+        cur.insert('Z');
+        assert_eq!(f.root_count(), 3);
+        assert_eq!(Forest::<char>::new().root_count(), 0);
+    }
+
+    #[test]
+    fn map_roots() {
+        let mut f: Forest<char> = Forest::try_from(vec![
+            (0, 'a'), (1, 'b'), (2, 'c'), (0, 'd'), (0, 'e'), (1, 'f'),
+        ]).unwrap();
+        f.map_roots(|c| *c = c.to_ascii_uppercase());
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [
+            (0, 'A'), (1, 'b'), (2, 'c'), (0, 'D'), (0, 'E'), (1, 'f'),
+        ]);
+
+        let mut f = Forest::<char>::new();
+        f.map_roots(|_| unreachable!());
+    }
+
+    #[test]
+    fn edges_size_hint() {
+        let mut f = Forest::new();
+        let mut cur = f.end_mut();
+        for i in 0..100 {
+            cur.insert_and_move(i);
+            cur.trailing_of();
+            for j in 0..9 {
+                cur.insert(j);
+            }
+            cur.move_next();
+        }
+        assert!(f.size_valid());
+
+        let mut iter = f.edges(ForestEdge::Leading);
+        assert_eq!(iter.size_hint(), (1000, Some(1000)));
+        iter.next();
+        assert_eq!(iter.size_hint(), (999, Some(999)));
+
+        let values: Vec<_> = f.edges(ForestEdge::Leading).collect();
+        assert_eq!(values.len(), 1000);
+        assert_eq!(values.capacity(), values.len());
+
+        f.invalidate_size();
+        assert_eq!(f.edges(ForestEdge::Trailing).size_hint(), (0, None));
+    }
+
+    #[test]
+    fn graft_at_path() {
+        let mut f = big_forest();
+        let subtree = Forest::try_from(vec![(0, 'X'), (1, 'Y'), (0, 'Z')]).unwrap();
+        assert_eq!(f.graft_at_path(&[0, 0, 0], subtree), Ok(()));
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABCFGHXYZDIJKE");
+        assert_eq!(f.size(), 14);
+        assert_eq!(find(&f, 'X').root_of(), find(&f, 'A'));
+
+        let mut f = big_forest();
+        assert_eq!(f.graft_at_path(&[0, 1], Forest::singleton('X')), Err(PathError { index: 1 }));
+        assert_eq!(f.graft_at_path(&[0, 0, 3], Forest::singleton('X')), Err(PathError { index: 2 }));
+        assert_eq!(f.graft_at_path(&[1], Forest::singleton('X')), Err(PathError { index: 0 }));
+        assert_eq!(fullorder(&f), fullorder(&big_forest()));
+
+        assert_eq!(f.graft_at_path(&[], Forest::singleton('X')), Ok(()));
+        assert_eq!(f.root_count(), 2);
+        assert_eq!(f.graft_at_path(&[0, 0, 2], Forest::singleton('Y')), Ok(()));
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABCFGHDIJKEYX");
+    }
+
+    #[test]
+    fn relation_to() {
+        use CursorRelation::*;
+        let mut f = big_forest();
+        f.graft_at_path(&[], Forest::try_from(vec![(0, 'Z'), (1, 'W')]).unwrap()).unwrap();
+        let c = |v| find(&f, v);
+
+        let mut c_trailing = c('C');
+        c_trailing.trailing_of();
+        assert_eq!(c('C').relation_to(&c_trailing), Same);
+        assert_eq!(c('F').relation_to(&c('I')), Before);
+        assert_eq!(c('I').relation_to(&c('F')), After);
+        assert_eq!(c('E').relation_to(&c('D')), After);
+        assert_eq!(c('B').relation_to(&c('H')), Ancestor);
+        assert_eq!(c('H').relation_to(&c('B')), Descendant);
+        assert_eq!(c('F').relation_to(&c('W')), DifferentTree);
+        assert_eq!(c('Z').relation_to(&c('A')), DifferentTree);
+        assert_eq!(f.root().relation_to(&c('W')), Ancestor);
+
+        let g = big_forest();
+        assert_eq!(c('A').relation_to(&find(&g, 'A')), DifferentTree);
+    }
+
+    #[test]
+    fn collapse_chains() {
+        let join = |a: &String, b: &String| format!("{}/{}", a, b);
+        let mut f: Forest<String> = Forest::try_from(vec![
+            (0, "a".to_string()), (1, "b".to_string()), (2, "c".to_string()),
+        ]).unwrap();
+        f.collapse_chains(join);
+        assert_eq!(preorder(&f), ["a/b/c"]);
+        assert_eq!(f.size(), 1);
+
+        let mut f: Forest<String> = Forest::try_from(
+            [(0, "src"), (1, "main"), (2, "java"), (3, "com"), (4, "x"), (2, "kotlin"), (0, "README")]
+                .map(|(d, s)| (d, s.to_string()))
+                .to_vec(),
+        ).unwrap();
+        f.collapse_chains(join);
+        assert_eq!(f.size(), 4);
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [
+            (0, "src/main".to_string()),
+            (1, "java/com/x".to_string()),
+            (1, "kotlin".to_string()),
+            (0, "README".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn offsets() {
+        let f = big_forest();
+        let d = find(&f, 'D');
+        assert_eq!(d.to_offset(), 6);
+        assert_eq!(f.cursor_at_offset(6), Some(d));
+        assert_eq!(f.cursor_at_offset(0), Some(f.begin()));
+        assert_eq!(f.cursor_at_offset(10).unwrap().current(), Some(&'E'));
+        assert_eq!(f.cursor_at_offset(11), None);
+        assert_eq!(f.end().to_offset(), 11);
+
+        for (offset, value) in "ABCFGHDIJKE".chars().enumerate() {
+            assert_eq!(find(&f, value).to_offset(), offset);
+        }
+    }
+
+    #[test]
+    fn split_children_at() {
+        let mut f = big_forest();
+        let mut cur = f.begin_mut();
+        cur.move_next();
+        let mut split = cur.split_children_at(1);
+        assert_eq!(cur.current(), Some(&mut 'B'));
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABCFGH");
+        assert_eq!(preorder(&split).into_iter().collect::<String>(), "DIJKE");
+        assert_eq!(split.root_count(), 2);
+        assert_eq!((f.size(), split.size()), (6, 5));
+
+        let mut cur = f.begin_mut();
+        assert!(cur.split_children_at(1).empty());
+        assert!(cur.split_children_at(5).empty());
+        assert_eq!(f.size(), 6);
+
+        assert!(f.end_mut().split_children_at(0).empty());
+        assert_eq!(f.size(), 6);
+
+        let mut split = f.root_mut().split_children_at(0);
+        assert!(f.empty());
+        assert_eq!(split.size(), 6);
+    }
+
+    #[test]
+    fn to_vec() {
+        let f = big_forest();
+        let preorder = f.to_vec_preorder();
+        assert_eq!(preorder.iter().collect::<String>(), "ABCFGHDIJKE");
+        assert_eq!(preorder.capacity(), 11);
+        assert_eq!(f.to_vec_postorder().iter().collect::<String>(), "FGHCIJKDEBA");
+        assert!(Forest::<char>::new().to_vec_preorder().is_empty());
+    }
+
+    #[test]
+    fn peek_mut() {
+        let mut f = big_forest();
+        let mut cur = f.begin_mut();
+        cur.move_next();
+        cur.move_next();
+        if let Some((edge, value)) = cur.peek_mut() {
+            assert_eq!((edge, *value), (ForestEdge::Leading, 'C'));
+            *value = 'c';
+        }
+        cur.trailing_of();
+        assert_eq!(cur.peek_mut(), Some((ForestEdge::Trailing, &mut 'c')));
+        assert_eq!(f.root_mut().peek_mut(), None);
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABcFGHDIJKE");
+    }
+
+    #[test]
+    fn count_where() {
+        let f = big_forest();
+        // B, C, F, G, H, D, J, K
+        assert_eq!(f.count_where(|c| !"AEIOU".contains(*c)), 8);
+        assert_eq!(f.count_where(|_| true), 11);
+        assert_eq!(Forest::<char>::new().count_where(|_| true), 0);
+    }
+
+    #[test]
+    fn child_axis() {
+        let f = big_forest();
+        let mut cur = find(&f, 'B');
+        assert!(cur.move_to_first_child());
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&'C')));
+        assert!(cur.move_to_next_child());
+        assert_eq!(cur.current(), Some(&'D'));
+        assert!(cur.move_to_next_child());
+        assert_eq!(cur.current(), Some(&'E'));
+        assert!(!cur.move_to_next_child());
+        assert_eq!(cur.current(), Some(&'E'));
+        assert!(!cur.move_to_first_child());
+        assert!(cur.move_to_prev_child());
+        assert_eq!(cur.current(), Some(&'D'));
+        assert!(cur.move_to_prev_child());
+        assert!(!cur.move_to_prev_child());
+        assert_eq!(cur.current(), Some(&'C'));
+
+        // Trailing edges move along the child axis too.
+        let mut cur = find(&f, 'C');
+        cur.trailing_of();
+        assert!(cur.move_to_next_child());
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&'D')));
+
+        let mut cur = f.root();
+        assert!(cur.move_to_first_child());
+        assert_eq!(cur.current(), Some(&'A'));
+        assert!(!cur.move_to_next_child());
+        assert!(!cur.move_to_prev_child());
+        let mut cur = f.root();
+        assert!(!cur.move_to_next_child());
+        assert!(!cur.move_to_prev_child());
+        assert!(!Forest::<char>::new().root().move_to_first_child());
+    }
+
+    #[test]
+    fn build_validated() {
+        #[derive(Debug, PartialEq)]
+        enum BuildError {
+            Invalid(ValidationError),
+            User,
+        }
+
+        impl From<ValidationError> for BuildError {
+            fn from(e: ValidationError) -> Self {
+                BuildError::Invalid(e)
+            }
+        }
+
+        let mut f = Forest::build_validated(|cur| {
+            cur.insert_and_move('A');
+            cur.trailing_of();
+            cur.insert('B');
+            cur.insert('C');
+            Ok::<_, BuildError>(())
+        }).unwrap();
+        assert_eq!(f.size(), 3);
+        assert_eq!(preorder(&f), ['A', 'B', 'C']);
+
+        let result = Forest::build_validated(|cur| {
+            cur.insert('A');
+            Err(BuildError::User)
+        });
+        assert_eq!(result.err(), Some(BuildError::User));
+
+        // Point A's leading edge straight at its trailing edge, skipping B.
+        let result = Forest::build_validated(|cur| {
+            cur.insert_and_move('A');
+            cur.trailing_of();
+            cur.insert('B');
+            let a = cur.cursor.node;
+            unsafe { (*a).base.leading_next = a; }
+            Ok::<_, BuildError>(())
+        });
+        assert!(matches!(result, Err(BuildError::Invalid(_))));
+    }
+
+    #[test]
+    fn find_all() {
+        let f = big_forest();
+        let found = f.find_all(|c| *c > 'H');
+        assert_eq!(found, [find(&f, 'I'), find(&f, 'J'), find(&f, 'K')]);
+        assert!(found.iter().all(|c| is_leading(c.edge())));
+
+        let mut j = found[1].root_of();
+        assert!(j.move_to_first_child());
+        assert_eq!(j.current(), Some(&'B'));
+        assert!(f.find_all(|c| *c == 'Z').is_empty());
+    }
+
+    #[test]
+    fn subtree_bounds() {
+        fn collect(bounds: (Cursor<char>, Cursor<char>)) -> String {
+            let (mut first, last) = bounds;
+            let mut values = String::new();
+            while first != last {
+                if is_leading(first.edge()) {
+                    values.push(*first.current().unwrap());
+                }
+                first.move_next();
+            }
+            values
+        }
+
+        let f = big_forest();
+        assert_eq!(collect(find(&f, 'C').subtree_bounds()), "CFGH");
+        let (_, last) = find(&f, 'C').subtree_bounds();
+        assert_eq!(last, find(&f, 'D'));
+        assert_eq!(collect(find(&f, 'K').subtree_bounds()), "K");
+        let (mut first, last) = find(&f, 'K').subtree_bounds();
+        first.move_next();
+        first.move_next();
+        assert_eq!(first, last);
+        assert_eq!(collect(find(&f, 'E').subtree_bounds()), "E");
+        assert_eq!(collect(find(&f, 'A').subtree_bounds()), "ABCFGHDIJKE");
+        assert_eq!(collect(f.root().subtree_bounds()), "ABCFGHDIJKE");
+        let (first, last) = f.root().subtree_bounds();
+        assert!(first == f.begin() && last == f.end());
+    }
+
+    #[test]
+    fn find_duplicate_subtrees() {
+        let f: Forest<char> = Forest::try_from(vec![
+            (0, 'A'), (1, 'C'), (2, 'F'), (2, 'G'), (1, 'B'), (2, 'C'), (3, 'F'), (3, 'G'),
+            (1, 'C'), (2, 'F'),
+        ]).unwrap();
+        let groups = f.find_duplicate_subtrees();
+        let c_group: Vec<_> = f.find_all(|c| *c == 'C').into_iter().take(2).collect();
+        assert_eq!(groups[0], c_group);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[1].len(), 3);
+        assert!(groups[1].iter().all(|c| c.current() == Some(&'F')));
+        assert_eq!(groups[2].len(), 2);
+        assert!(groups[2].iter().all(|c| c.current() == Some(&'G')));
+
+        assert!(Forest::try_from(vec![(0, 'A'), (1, 'B'), (0, 'B'), (1, 'A')])
+            .unwrap()
+            .find_duplicate_subtrees()
+            .is_empty());
+    }
+
+    #[test]
+    fn remove_all() {
+        let mut data = std::rc::Rc::new(());
+        let mut f = big_forest_with(|c| (c, data.clone()));
+        let ids: Vec<_> = f
+            .find_all(|(c, _)| "CGIJ".contains(*c))
+            .iter()
+            .map(Cursor::id)
+            .collect();
+        let other = big_forest();
+        let ids = [ids[1], ids[0], ids[2], ids[3], ids[0], find(&other, 'A').id()];
+        assert_eq!(f.remove_all(&ids), 6);
+        assert_eq!(preorder(&f).into_iter().map(|(c, _)| c).collect::<String>(), "ABDKE");
+        assert_eq!(f.size(), 5);
+        assert_eq!(std::rc::Rc::strong_count(&data), 6);
+
+        assert_eq!(f.remove_all(&[]), 0);
+        let a = f.begin().id();
+        assert_eq!(f.remove_all(&[a]), 5);
+        assert!(f.empty());
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    }
+
+    #[test]
+    fn reverse() {
+        let mut f = big_forest();
+        f.reverse();
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABEDKJICHGF");
+        assert_eq!(f.size(), 11);
+        f.debug_assert_valid();
+        f.reverse();
+        assert_eq!(fullorder(&f), fullorder(&big_forest()));
+
+        let mut f = Forest::try_from(vec![(0, 1), (1, 2), (0, 3), (0, 4), (1, 5), (1, 6)]).unwrap();
+        f.reverse();
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [
+            (0, 4), (1, 6), (1, 5), (0, 3), (0, 1), (1, 2),
+        ]);
+
+        let mut f = Forest::<char>::new();
+        f.reverse();
+        assert!(f.empty());
+    }
+
+    #[test]
+    fn same_tree() {
+        let f = big_forest();
+        assert!(find(&f, 'F').same_tree(&find(&f, 'I')));
+        assert!(find(&f, 'A').same_tree(&find(&f, 'K')));
+
+        let g = big_forest();
+        assert!(!find(&f, 'F').same_tree(&find(&g, 'F')));
+
+        let f = Forest::try_from(vec![(0, 'A'), (1, 'B'), (0, 'C'), (1, 'D')]).unwrap();
+        assert!(find(&f, 'B').same_tree(&find(&f, 'A')));
+        assert!(!find(&f, 'B').same_tree(&find(&f, 'D')));
+        assert!(!find(&f, 'A').same_tree(&find(&f, 'C')));
+    }
+
+    #[test]
+    fn to_tree_lines() {
+        let f = Forest::try_from(vec![
+            (0, "src"), (1, "bin"), (2, "main.rs"), (1, "lib.rs"), (0, "docs"), (1, "a.md"), (1, "b"), (2, "c.md"),
+        ]).unwrap();
+        assert_eq!(f.to_tree_lines(), [
+            "src",
+            "\u{251c}\u{2500}\u{2500} bin",
+            "\u{2502}   \u{2514}\u{2500}\u{2500} main.rs",
+            "\u{2514}\u{2500}\u{2500} lib.rs",
+            "docs",
+            "\u{251c}\u{2500}\u{2500} a.md",
+            "\u{2514}\u{2500}\u{2500} b",
+            "    \u{2514}\u{2500}\u{2500} c.md",
+        ]);
+        assert!(Forest::<char>::new().to_tree_lines().is_empty());
+    }
+
+    #[test]
+    fn group_under() {
+        let mut f: Forest<char> = Forest::try_from(vec![
+            (0, 'a'), (1, 'b'), (0, 'c'), (0, 'e'), (1, 'f'), (1, 'i'), (0, 'g'), (0, 'o'),
+        ]).unwrap();
+        f.group_under('V', |c| "aeiou".contains(*c));
+        assert_eq!(f.size(), 9);
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [
+            (0, 'c'), (0, 'g'), (0, 'V'), (1, 'a'), (2, 'b'), (1, 'e'), (2, 'f'), (2, 'i'), (1, 'o'),
+        ]);
+
+        let mut f = Forest::new();
+        f.group_under('V', |_| true);
+        assert_eq!(preorder(&f), ['V']);
+    }
+
+    #[test]
+    fn cursor_iterator() {
+        let f = big_forest();
+        let mut walked = Vec::new();
+        for (edge, v) in f.begin() {
+            walked.push((edge, *v));
+        }
+        assert_eq!(walked, fullorder(&f));
+        assert_eq!(f.root().count(), 22);
+        assert_eq!(find(&f, 'K').map(|(_, v)| *v).collect::<String>(), "KKDEEBA");
+
+        let mut end = f.end();
+        assert_eq!(end.next(), None);
+        assert!(end == f.end());
+        assert_eq!(Forest::<char>::new().root().next(), None);
+    }
+
+    #[test]
+    fn memoized_fold() {
+        let f = big_forest();
+        let counts = f.memoized_fold(|_, children: &[usize]| 1 + children.iter().sum::<usize>());
+        assert_eq!(counts.len(), 11);
+        assert_eq!(counts[&find(&f, 'A').id()], 11);
+        assert_eq!(counts[&find(&f, 'B').id()], 10);
+        assert_eq!(counts[&find(&f, 'D').id()], 4);
+        assert_eq!(counts[&find(&f, 'K').id()], 1);
+
+        let labels = f.memoized_fold(|v, children: &[String]| format!("{}{}", v, children.concat()));
+        assert_eq!(labels[&find(&f, 'C').id()], "CFGH");
+
+        assert!(Forest::<char>::new().memoized_fold(|_, _: &[()]| ()).is_empty());
+    }
+
+    #[test]
+    fn truncate_level() {
+        let mut f = big_forest();
+        let mut cursor = f.begin_mut();
+        for _ in 0..10 {
+            cursor.move_next();
+        }
+        assert_eq!(cursor.current(), Some(&mut 'D'));
+        cursor.truncate_level();
+        assert_eq!(cursor.edge(), ForestEdge::Trailing);
+        assert_eq!(cursor.current(), Some(&mut 'B'));
+        assert_eq!(f.size(), 6);
+        assert_eq!(preorder(&f), ['A', 'B', 'C', 'F', 'G', 'H']);
+
+        let mut f = Forest::try_from(vec![(0, 'a'), (0, 'b'), (1, 'c'), (0, 'd')]).unwrap();
+        let mut cursor = f.begin_mut();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 'b'));
+        cursor.truncate_level();
+        assert!(cursor.cursor == cursor.forest.unsafe_end());
+        assert_eq!(preorder(&f), ['a']);
+
+        let mut f = big_forest();
+        f.root_mut().truncate_level();
+        assert_eq!(f.size(), 11);
+    }
+
+    #[test]
+    fn scan_fold() {
+        let f = Forest::try_from(vec![
+            (0, 'A'), (1, 'B'), (2, 'C'), (1, 'D'), (0, 'E'), (1, 'F'),
+        ]).unwrap();
+        let (numbers, sizes) = f.scan_fold(
+            Vec::new(),
+            |numbers: &mut Vec<(char, usize)>, v| numbers.push((*v, numbers.len())),
+            |_, v, children: Vec<(char, usize)>| (*v, 1 + children.iter().map(|c| c.1).sum::<usize>()),
+        );
+        assert_eq!(numbers, [('A', 0), ('B', 1), ('C', 2), ('D', 3), ('E', 4), ('F', 5)]);
+        assert_eq!(sizes, [('A', 4), ('E', 2)]);
+
+        let (state, results) = Forest::<char>::new().scan_fold(0, |_, _| (), |_, _, _: Vec<()>| ());
+        assert_eq!(state, 0);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn widest_node() {
+        let f = big_forest();
+        assert_eq!(f.widest_node().unwrap().current(), Some(&'B'));
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c'), (1, 'd'), (1, 'e')]).unwrap();
+        assert_eq!(f.widest_node().unwrap().current(), Some(&'c'));
+
+        let f = Forest::try_from(vec![(0, 'a'), (0, 'b')]).unwrap();
+        assert_eq!(f.widest_node().unwrap().current(), Some(&'a'));
+        assert!(Forest::<char>::new().widest_node().is_none());
     }
 
-    pub fn empty(&self) -> bool {
-        self.begin() == self.end()
+    #[test]
+    fn from_edge_stream() {
+        let f = big_forest();
+        let g = Forest::from_edge_stream(fullorder(&f)).unwrap();
+        assert_eq!(fullorder(&g), fullorder(&f));
+        g.debug_assert_valid();
+
+        let stream = vec![(ForestEdge::Leading, 'a'), (ForestEdge::Trailing, 'a'), (ForestEdge::Trailing, 'b')];
+        assert_eq!(Forest::from_edge_stream(stream).err(), Some(EdgeStreamError { index: 2 }));
+        let stream = vec![(ForestEdge::Leading, 'a'), (ForestEdge::Leading, 'b'), (ForestEdge::Trailing, 'b')];
+        assert_eq!(Forest::from_edge_stream(stream).err(), Some(EdgeStreamError { index: 3 }));
+        assert!(Forest::<char>::from_edge_stream(vec![]).unwrap().empty());
     }
 
-    pub fn root(&self) -> Cursor<T> {
-        Cursor { forest: self, cursor: self.unsafe_root() }
+    #[test]
+    fn assign_depths() {
+        let mut f = big_forest_with(|c| (c, usize::MAX));
+        f.assign_depths(|(_, depth), d| *depth = d);
+        let depths: Vec<_> = f.edges(ForestEdge::Leading).copied().collect();
+        assert!(depths.contains(&('F', 3)));
+        assert!(depths.contains(&('A', 0)));
+        assert!(depths.contains(&('E', 2)));
+
+        let mut f: Forest<(char, usize)> = Forest::try_from(vec![
+            (0, ('a', 9)), (1, ('b', 9)), (2, ('F', 9)), (0, ('c', 9)),
+        ]).unwrap();
+        f.assign_depths(|(_, depth), d| *depth = d);
+        assert_eq!(f.to_vec_preorder(), [('a', 0), ('b', 1), ('F', 2), ('c', 0)]);
     }
 
-    pub fn root_mut(&mut self) -> CursorMut<T> {
-        let cursor = self.unsafe_root();
-        CursorMut { forest: self, cursor }
+    #[test]
+    fn siblings_after_and_before() {
+        let f = big_forest();
+        let c = find(&f, 'C');
+        assert_eq!(c.siblings_after().collect::<String>(), "DE");
+        assert_eq!(c.siblings_before().count(), 0);
+
+        let j = find(&f, 'J');
+        assert_eq!(j.siblings_after().collect::<String>(), "K");
+        assert_eq!(j.siblings_before().collect::<String>(), "I");
+        assert_eq!(find(&f, 'E').siblings_after().count(), 0);
+        assert_eq!(find(&f, 'E').siblings_before().collect::<String>(), "CD");
+        assert_eq!(find(&f, 'A').siblings_after().count(), 0);
+        assert_eq!(f.root().siblings_after().count(), 0);
+        assert_eq!(f.root().siblings_before().count(), 0);
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c'), (0, 'd')]).unwrap();
+        assert_eq!(find(&f, 'a').siblings_after().collect::<String>(), "cd");
+        assert_eq!(find(&f, 'd').siblings_before().collect::<String>(), "ac");
     }
 
-    pub fn begin(&self) -> Cursor<T> {
-        Cursor { forest: self, cursor: self.unsafe_begin() }
+    #[test]
+    fn size_is_cached() {
+        let mut f = big_forest();
+        assert!(f.size_is_cached());
+
+        let mut g = big_forest();
+        g.invalidate_size();
+        assert!(!g.size_is_cached());
+        f.end_mut().splice(g);
+        assert!(!f.size_is_cached());
+        assert_eq!(f.size(), 22);
+        assert!(f.size_is_cached());
+
+        // An empty forest's count is trusted, and a stale one is recounted.
+        let mut f = Forest::<char>::new();
+        assert!(f.size_is_cached());
+        f.invalidate_size();
+        assert!(!f.size_valid());
+        assert_eq!(f.size(), 0);
+        assert!(f.size_is_cached());
     }
 
-    pub fn begin_mut(&mut self) -> CursorMut<T> {
-        let mut c = self.root_mut();
-        c.move_next();
-        c
+    #[test]
+    fn iter() {
+        let f = big_forest();
+        let mut names = String::new();
+        for name in &f {
+            names.push(*name);
+        }
+        assert_eq!(names, "ABCFGHDIJKE");
+        assert_eq!(f.iter().size_hint(), (11, Some(11)));
+
+        let f = Forest::<String>::new();
+        assert_eq!((&f).into_iter().next(), None);
     }
 
-    pub fn end(&self) -> Cursor<T> {
-        Cursor { forest: self, cursor: self.unsafe_end() }
+    #[test]
+    fn preorder_and_postorder() {
+        let f = big_forest();
+        assert_eq!(f.preorder().collect::<String>(), "ABCFGHDIJKE");
+        assert_eq!(f.postorder().collect::<String>(), "FGHCIJKDEBA");
+        assert_eq!(f.postorder().size_hint(), (11, Some(11)));
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c')]).unwrap();
+        assert_eq!(f.postorder().collect::<String>(), "bac");
+        assert_eq!(Forest::<char>::new().postorder().next(), None);
     }
 
-    pub fn end_mut(&mut self) -> CursorMut<T> {
-        let cursor = self.unsafe_end();
-        CursorMut { forest: self, cursor }
+    #[test]
+    fn children() {
+        let f = big_forest();
+        assert_eq!(find(&f, 'B').children().collect::<String>(), "CDE");
+        assert_eq!(find(&f, 'A').children().collect::<String>(), "B");
+        assert_eq!(find(&f, 'E').children().count(), 0);
+        assert_eq!(f.root().children().collect::<String>(), "A");
+
+        let mut c = find(&f, 'D');
+        c.trailing_of();
+        assert_eq!(c.children().collect::<String>(), "IJK");
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c')]).unwrap();
+        assert_eq!(f.root().children().collect::<String>(), "ac");
+        assert_eq!(Forest::<char>::new().root().children().count(), 0);
     }
 
-    pub fn clear(&mut self) {
-        let begin = self.unsafe_begin();
-        let end = self.unsafe_end();
-        unsafe { begin.erase_range(end); }
-        self.size = 0;
+    #[test]
+    fn double_ended_iterators() {
+        let f = big_forest();
+        assert_eq!(f.iter().rev().collect::<String>(), "EKJIDHGFCBA");
+        assert_eq!(f.postorder().rev().collect::<String>(), "ABEDKJICHGF");
+        assert_eq!(f.edges(ForestEdge::Leading).rev().count(), 11);
+
+        // Alternating ends meet in the middle without repeating a node.
+        let mut iter = f.iter();
+        let mut front = String::new();
+        let mut back = String::new();
+        while let Some(v) = iter.next() {
+            front.push(*v);
+            match iter.next_back() {
+                Some(v) => back.push(*v),
+                None => break,
+            }
+            assert_eq!(iter.size_hint().0, 11 - front.len() - back.len());
+        }
+        assert_eq!(front, "ABCFGH");
+        assert_eq!(back, "EKJID");
+
+        let mut iter = f.postorder();
+        assert_eq!(iter.next_back(), Some(&'A'));
+        assert_eq!(iter.next(), Some(&'F'));
+        assert_eq!(iter.by_ref().rev().collect::<String>(), "BEDKJICHG");
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c')]).unwrap();
+        assert_eq!(f.postorder().rev().collect::<String>(), "cab");
+        assert_eq!(Forest::<char>::new().iter().next_back(), None);
+        assert_eq!(Forest::<char>::new().postorder().next_back(), None);
     }
 
-    fn unsafe_root(&self) -> RawCursor<T> {
-        RawCursor {
-            node: self.tail_mut(),
-            edge: ForestEdge::Leading,
+    #[test]
+    fn erase_range_from_trailing_edge() {
+        let mut f = big_forest();
+        let first = find(&f, 'C').cursor.trailing_of();
+        let last = find(&f, 'B').cursor.trailing_of();
+        unsafe { first.erase_range(last); }
+        f.invalidate_size();
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABCFGH");
+        assert_eq!(f.size(), 6);
+        f.debug_assert_valid();
+
+        // Trailing edges of nodes outside the range leave them in place.
+        let mut f = big_forest();
+        let first = find(&f, 'H').cursor.trailing_of();
+        let last = find(&f, 'A').cursor.trailing_of();
+        unsafe { first.erase_range(last); }
+        f.invalidate_size();
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABCFGH");
+        f.debug_assert_valid();
+    }
+
+    #[test]
+    fn remove_interior_node() {
+        let mut f = big_forest();
+        let mut cur = f.begin_mut();
+        for _ in 0..10 {
+            cur.move_next();
         }
+        assert_eq!(cur.current(), Some(&mut 'D'));
+        cur.erase();
+        assert!(f.size_is_cached());
+        assert_eq!(f.size(), 10);
+        assert_eq!(f.edges(ForestEdge::Leading).count(), 10);
+        assert_eq!(find(&f, 'B').children().collect::<String>(), "CIJKE");
     }
 
-    fn unsafe_begin(&self) -> RawCursor<T> {
-        unsafe {
-            let mut c = self.unsafe_root();
-            c.move_next();
-            c
+    #[test]
+    fn erase_during_traversal() {
+        let mut f = big_forest();
+        let mut visited = String::new();
+        let mut cur = f.begin_mut();
+        while cur.cursor != cur.forest.unsafe_end() {
+            if cur.edge() == ForestEdge::Leading {
+                let value = *cur.current().unwrap();
+                visited.push(value);
+                if value == 'G' || value == 'J' {
+                    cur.erase();
+                    continue;
+                }
+            }
+            cur.move_next();
         }
+        assert_eq!(visited, "ABCFGHDIJKE");
+        assert_eq!(f.size(), 9);
+        assert_eq!(preorder(&f).into_iter().collect::<String>(), "ABCFHDIKE");
+
+        // Erasing on a trailing edge moves past it.
+        let mut f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c')]).unwrap();
+        let mut cur = f.begin_mut();
+        cur.trailing_of();
+        cur.erase();
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&mut 'c')));
+        assert_eq!(preorder(&f), ['b', 'c']);
+
+        let mut f = big_forest();
+        f.root_mut().erase();
+        f.end_mut().erase();
+        assert_eq!(f.size(), 11);
     }
 
-    fn unsafe_end(&self) -> RawCursor<T> {
-        RawCursor::new(self.tail_mut(), ForestEdge::Trailing)
+    #[test]
+    fn clone() {
+        let f = big_forest();
+        let mut g = f.clone();
+        assert_eq!(fullorder(&g), fullorder(&f));
+        assert!(g.size_is_cached());
+        assert_eq!(g.size(), 11);
+        g.begin_mut().erase();
+        assert_eq!(preorder(&f).len(), 11);
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c')]).unwrap();
+        assert_eq!(f.clone().into_depth_pairs().collect::<Vec<_>>(), [(0, 'a'), (1, 'b'), (0, 'c')]);
+        assert!(Forest::<char>::new().clone().empty());
     }
 
-    fn tail_mut(&self) -> *mut Node<T> {
-        self.tail
+    #[test]
+    fn forest_eq() {
+        let f = big_forest();
+        let mut g = big_forest();
+        assert!(f == g);
+        g.invalidate_size();
+        assert!(f == g);
+
+        let mut cur = g.end_mut();
+        cur.insert('L');
+        assert!(f != g);
+
+        // Same preorder values in a different shape.
+        let a = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c')]).unwrap();
+        let b = Forest::try_from(vec![(0, 'a'), (0, 'b'), (0, 'c')]).unwrap();
+        assert!(a != b);
+        assert!(a == a.clone());
+        assert!(Forest::<char>::new() == Forest::new());
+        assert!(Forest::<char>::new() != a);
     }
-}
 
-impl<T> Drop for Forest<T> {
-    fn drop(&mut self) {
-        self.clear();
+    #[test]
+    fn forest_debug() {
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (0, 'e')]).unwrap();
+        assert_eq!(format!("{:?}", f), "Forest [\n    'a'\n        'b'\n            'c'\n        'd'\n    'e'\n]");
+        assert_eq!(format!("{:?}", Forest::<char>::new()), "Forest []");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn from_iter() {
+        let mut f: Forest<i32> = (0..5).collect();
+        assert!(f.size_is_cached());
+        assert_eq!(f.size(), 5);
+        assert_eq!(f.root_count(), 5);
+        assert_eq!(f.iter().copied().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        assert!(std::iter::empty::<i32>().collect::<Forest<_>>().empty());
+    }
 
-    fn print(f: &Forest<(char, std::rc::Rc<()>)>) {
-        struct Tabs(usize);
+    #[test]
+    fn extend() {
+        let mut f = big_forest();
+        f.extend(['X', 'Y']);
+        assert_eq!(f.size(), 13);
+        assert_eq!(f.root().children().collect::<String>(), "AXY");
+        assert_eq!(f.iter().collect::<String>(), "ABCFGHDIJKEXY");
+
+        let mut f = Forest::new();
+        f.extend(0..3);
+        f.extend(3..5);
+        assert_eq!(f.size(), 5);
+        assert_eq!(f.iter().copied().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    }
 
-        impl std::fmt::Display for Tabs {
-            fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-                for _ in 0..self.0 {
-                    write!(f, "\t")?;
-                }
-                Ok(())
-            }
+    #[test]
+    fn into_iter() {
+        let f = big_forest();
+        let mut values = String::new();
+        for value in f {
+            values.push(value);
         }
+        assert_eq!(values, "ABCFGHDIJKE");
 
-        let mut cur = f.begin();
-        let mut depth = 0;
-        while cur != f.end() {
-            use ForestEdge::*;
-            match (cur.edge(), cur.current().unwrap().0) {
-                (Leading, value) => {
-                    println!("{}<{}>", Tabs(depth), value);
-                    depth += 1;
-                }
-                (Trailing, value) => {
-                    depth -= 1;
-                    println!("{}</{}>", Tabs(depth), value);
-                }
-            }
-            cur.move_next();
+        let mut data = std::rc::Rc::new(());
+        let f = big_forest_with(|c| (c, data.clone()));
+        let mut iter = f.into_iter();
+        assert_eq!(iter.size_hint(), (11, Some(11)));
+        let taken: Vec<_> = iter.by_ref().take(4).collect();
+        assert_eq!(taken.iter().map(|(c, _)| *c).collect::<String>(), "ABCF");
+        assert_eq!(iter.size_hint(), (7, Some(7)));
+        assert_eq!(std::rc::Rc::strong_count(&data), 1 + 11);
+        drop(taken);
+        assert_eq!(std::rc::Rc::strong_count(&data), 1 + 7);
+        drop(iter);
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    }
+
+    #[test]
+    fn reverse_two_levels() {
+        let mut f = Forest::try_from(vec![
+            (0, 'a'), (1, 'b'), (1, 'c'), (0, 'd'), (1, 'e'), (1, 'f'), (1, 'g'),
+        ]).unwrap();
+        f.reverse();
+        assert_eq!(f.size(), 7);
+        assert!(f == Forest::try_from(vec![
+            (0, 'd'), (1, 'g'), (1, 'f'), (1, 'e'), (0, 'a'), (1, 'c'), (1, 'b'),
+        ]).unwrap());
+    }
+
+    #[test]
+    fn append() {
+        let mut f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c'), (0, 'd')]).unwrap();
+        let g = Forest::try_from(vec![(0, 'e'), (0, 'f'), (1, 'g'), (0, 'h')]).unwrap();
+        f.append(g);
+        assert_eq!(f.size(), 8);
+        assert_eq!(f.root().children().collect::<String>(), "acdefh");
+        assert_eq!(f.iter().collect::<String>(), "abcdefgh");
+
+        let mut g = big_forest();
+        g.invalidate_size();
+        f.append(g);
+        assert!(!f.size_is_cached());
+        assert_eq!(f.size(), 19);
+
+        let mut f = Forest::new();
+        f.append(Forest::new());
+        assert!(f.empty());
+        f.append(big_forest());
+        assert!(f == big_forest());
+    }
+
+    #[test]
+    fn depth() {
+        let f = big_forest();
+        let depths: Vec<_> = "ABCFDKE".chars().map(|c| find(&f, c).depth()).collect();
+        assert_eq!(depths, [0, 1, 2, 3, 2, 3, 2]);
+
+        let mut k = find(&f, 'K');
+        k.trailing_of();
+        assert_eq!(k.depth(), 3);
+        assert_eq!(f.root().depth(), 0);
+        assert_eq!(f.end().depth(), 0);
+    }
+
+    #[test]
+    fn to_parent() {
+        let f = big_forest();
+        let mut cur = find(&f, 'J');
+        let mut path = vec![*cur.current().unwrap()];
+        while cur.to_parent() {
+            assert_eq!(cur.edge(), ForestEdge::Leading);
+            path.push(*cur.current().unwrap());
         }
+        assert_eq!(path, ['J', 'D', 'B', 'A']);
+        assert!(cur == find(&f, 'A'));
+
+        let mut root = f.root();
+        assert!(!root.to_parent());
+
+        let mut f = big_forest();
+        let mut cur = f.end_mut();
+        for _ in 0..3 {
+            cur.move_prev();
+        }
+        assert_eq!(cur.current(), Some(&mut 'E'));
+        assert!(cur.to_parent());
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&mut 'B')));
+        assert!(cur.to_parent());
+        assert!(!cur.to_parent());
+        assert_eq!(cur.current(), Some(&mut 'A'));
     }
 
     #[test]
-    fn big_test_forest() {
+    fn has_children_and_is_leaf() {
+        let mut f = big_forest();
+        let d = find(&f, 'D');
+        assert!(d.has_children() && !d.is_leaf());
+        let k = find(&f, 'K');
+        assert!(!k.has_children() && k.is_leaf());
+        assert!(!f.root().has_children() && !f.root().is_leaf());
+        assert!(!f.end().has_children() && !f.end().is_leaf());
+
+        let cur = f.begin_mut();
+        assert!(cur.has_children() && !cur.is_leaf());
+        let mut cur = f.end_mut();
+        cur.move_prev();
+        cur.move_prev();
+        cur.move_prev();
+        assert!(cur.is_leaf() && !cur.has_children());
+    }
+
+    #[test]
+    fn filter() {
+        let f = big_forest();
+        assert_eq!(f.filter(|c| *c != 'D').collect::<String>(), "ABCFGHE");
+        assert_eq!(f.filter(|c| *c != 'G' && *c != 'C').collect::<String>(), "ABDIJKE");
+        assert_eq!(f.filter(|_| true).collect::<String>(), "ABCFGHDIJKE");
+        assert_eq!(f.filter(|c| *c != 'A').count(), 0);
+
+        let f = Forest::try_from(vec![(0, 1), (1, 2), (0, 3), (1, 4), (0, 5)]).unwrap();
+        assert_eq!(f.filter(|v| *v != 3).copied().collect::<Vec<_>>(), [1, 2, 5]);
+    }
+
+    #[test]
+    fn map() {
+        let f: Forest<i32> = Forest::try_from(vec![(0, 1), (1, 2), (2, 3), (1, 4), (0, 5)]).unwrap();
+        let mut g = f.map(|v| format!("#{}", v * 10));
+        assert_eq!(g.size(), 5);
+        assert_eq!(g.into_depth_pairs().collect::<Vec<_>>(), [
+            (0, "#10".to_string()), (1, "#20".to_string()), (2, "#30".to_string()), (1, "#40".to_string()), (0, "#50".to_string()),
+        ]);
+
+        let f = big_forest();
+        assert!(f.map(|c| c.to_ascii_lowercase()).map(|c| c.to_ascii_uppercase()) == f);
+        assert!(Forest::<i32>::new().map(|v| *v).empty());
+    }
+
+    #[test]
+    fn insert_parent() {
+        let mut f: Forest<char> = "abcd".chars().collect();
+        let mut cur = f.begin_mut();
+        cur.move_next();
+        cur.move_next();
+        cur.insert_parent('P', 2);
+        assert_eq!(cur.current(), Some(&mut 'P'));
+        assert_eq!(f.size(), 5);
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [(0, 'a'), (0, 'P'), (1, 'b'), (1, 'c'), (0, 'd')]);
+
+        let mut f = big_forest();
+        let mut cur = f.begin_mut();
+        cur.insert_parent('P', 0);
+        assert_eq!(f.into_depth_pairs().map(|(d, _)| d).take(3).collect::<Vec<_>>(), [0, 0, 1]);
+
+        // A count past the last sibling wraps the rest of the level.
+        let mut f = big_forest();
+        let mut cur = f.begin_mut();
+        cur.move_next();
+        cur.insert_parent('P', 5);
+        assert_eq!(f.size(), 12);
+        assert_eq!(find(&f, 'A').children().collect::<String>(), "P");
+        assert_eq!(find(&f, 'P').children().collect::<String>(), "B");
+        assert_eq!(find(&f, 'B').depth(), 2);
+
+        let mut f: Forest<char> = "abc".chars().collect();
+        let mut cur = f.root_mut();
+        cur.insert_parent('P', 2);
+        assert_eq!(cur.current(), Some(&mut 'P'));
+        f.debug_assert_valid();
+        assert_eq!(f.len(), 4);
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [(0, 'P'), (1, 'a'), (1, 'b'), (0, 'c')]);
+
+        let mut f: Forest<char> = "ab".chars().collect();
+        let mut cur = f.end_mut();
+        cur.insert_parent('P', 2);
+        assert_eq!(cur.current(), Some(&mut 'P'));
+        f.debug_assert_valid();
+        assert_eq!(f.len(), 3);
+        assert_eq!(f.into_depth_pairs().collect::<Vec<_>>(), [(0, 'a'), (0, 'b'), (0, 'P')]);
+    }
+
+    #[test]
+    fn clear_deep_tree() {
         let mut data = std::rc::Rc::new(());
+        let mut f = Forest::new();
+        let mut cur = f.end_mut();
+        for i in 0..10_000 {
+            cur.insert_and_move((i, data.clone()));
+            cur.trailing_of();
+            cur.insert((-i, data.clone()));
+        }
+        assert_eq!(f.size(), 20_000);
+        assert_eq!(std::rc::Rc::strong_count(&data), 1 + 20_000);
+        f.clear();
+        assert!(f.empty());
+        assert_eq!(f.size(), 0);
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
 
-        let mut c = Forest::new();
-        let mut cur = c.end_mut();
-        cur.insert_and_move(('C', data.clone()));
-        cur.trailing_of();
+        // The forest is still usable after clearing.
+        f.end_mut().insert((0, data.clone()));
+        assert_eq!(f.size(), 1);
+        drop(f);
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    }
 
-        cur.insert(('F', data.clone()));
-        cur.insert(('G', data.clone()));
-        cur.insert(('H', data.clone()));
+    #[test]
+    fn current_outlives_cursor_moves() {
+        let f = big_forest();
+        let (a, b) = {
+            let mut cur = f.begin();
+            let a = cur.current().unwrap();
+            cur.move_next();
+            let b = cur.current().unwrap();
+            cur.move_next();
+            (a, b)
+        };
+        assert_eq!((*a, *b), ('A', 'B'));
+    }
 
-        let mut d = Forest::new();
-        let mut cur = d.end_mut();
-        cur.insert_and_move(('D', data.clone()));
-        cur.trailing_of();
+    #[test]
+    fn send_and_sync() {
+        let f: Forest<i32> = Forest::try_from(vec![(0, 1), (1, 2), (0, 3)]).unwrap();
+        let f = std::thread::spawn(move || {
+            assert_eq!(f.iter().sum::<i32>(), 6);
+            f
+        }).join().unwrap();
+
+        let shared = std::sync::Arc::new(f);
+        let sums: Vec<i32> = (0..2).map(|_| {
+            let f = shared.clone();
+            std::thread::spawn(move || f.postorder().sum()).join().unwrap()
+        }).collect();
+        assert_eq!(sums, [6, 6]);
+    }
 
-        cur.insert(('I', data.clone()));
-        cur.insert(('J', data.clone()));
-        cur.insert(('K', data.clone()));
+    #[test]
+    fn len() {
+        let mut f = big_forest();
+        f.invalidate_size();
+        let shared = &f;
+        let begin = shared.begin();
+        assert_eq!(shared.len(), 11);
+        assert_eq!(begin.current(), Some(&'A'));
+        assert!(!f.size_is_cached());
+        assert_eq!(f.size(), f.len());
+    }
 
-        let mut e = Forest::new();
-        let mut cur = e.end_mut();
-        cur.insert_and_move(('E', data.clone()));
+    #[test]
+    fn is_empty_and_len() {
+        let mut f = Forest::new();
+        assert!(f.is_empty());
+        assert_eq!(f.len(), 0);
+        f.extend("abc".chars());
+        f.begin_mut().insert('z');
+        assert!(!f.is_empty());
+        assert_eq!(f.len(), 4);
+    }
 
-        let mut b = Forest::new();
-        let mut cur = b.end_mut();
-        cur.insert_and_move(('B', data.clone()));
-        cur.trailing_of();
+    #[test]
+    fn iter_exact_size() {
+        let mut f = big_forest();
+        f.invalidate_size();
+        let mut iter = f.iter();
+        assert_eq!(iter.len(), 11);
+        let mut calls = 0;
+        while iter.next().is_some() {
+            calls += 1;
+            assert_eq!(iter.len(), 11 - calls);
+        }
+        assert_eq!(calls, 11);
+
+        let mut iter = f.iter();
+        iter.next_back();
+        iter.next();
+        assert_eq!(iter.len(), 9);
+        assert_eq!(iter.collect::<String>(), "BCFGHDIJK");
+        assert_eq!(Forest::<char>::new().iter().len(), 0);
+    }
 
-        cur.splice(c);
-        cur.splice(d);
-        cur.splice(e);
+    #[test]
+    fn flatten() {
+        let f = big_forest();
+        let pairs = f.flatten();
+        assert_eq!(pairs.iter().map(|(d, _)| *d).collect::<Vec<_>>(), [0, 1, 2, 3, 3, 3, 2, 3, 3, 3, 2]);
+        assert_eq!(pairs.iter().map(|(_, c)| *c).collect::<String>(), "ABCFGHDIJKE");
 
-        let mut a = Forest::new();
-        let mut cur = a.end_mut();
-        cur.insert_and_move(('A', data.clone()));
-        cur.trailing_of();
+        let mut data = std::rc::Rc::new(());
+        let pairs = big_forest_with(|c| (c, data.clone())).flatten();
+        assert_eq!(std::rc::Rc::strong_count(&data), 1 + 11);
+        drop(pairs);
+        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+        assert!(Forest::<char>::new().flatten().is_empty());
+    }
 
-        cur.splice(b);
+    #[test]
+    fn unflatten() {
+        let f = big_forest();
+        assert!(Forest::unflatten(big_forest().flatten()) == f);
+
+        let f = Forest::unflatten(vec![(0, 'a'), (2, 'b'), (5, 'c'), (1, 'd'), (0, 'e')]);
+        assert_eq!(f.flatten(), [(0, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (0, 'e')]);
+        assert_eq!(Forest::unflatten(vec![(3, 'a')]).flatten(), [(0, 'a')]);
+        assert!(Forest::<char>::unflatten(vec![]).is_empty());
+    }
 
-        print(&a);
+    #[test]
+    fn rev_preorder() {
+        let f = big_forest();
+        let mut expected = f.preorder().collect::<Vec<_>>();
+        expected.reverse();
+        assert_eq!(f.rev_preorder().collect::<Vec<_>>(), expected);
+
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (0, 'c'), (1, 'd')]).unwrap();
+        assert_eq!(f.rev_preorder().collect::<String>(), "dcba");
+        assert_eq!(Forest::<char>::new().rev_preorder().next(), None);
+    }
 
-        assert_eq!(a.size(), 11);
+    #[test]
+    fn detach_subtree() {
+        let mut f = big_forest();
+        let mut cur = f.begin_mut();
+        for _ in 0..10 {
+            cur.move_next();
+        }
+        assert_eq!(cur.current(), Some(&mut 'D'));
+        let mut detached = cur.detach_subtree();
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&mut 'E')));
+        assert_eq!(f.size(), 7);
+        assert_eq!(f.iter().collect::<String>(), "ABCFGHE");
+        assert_eq!(detached.size(), 4);
+        assert_eq!(detached.flatten(), [(0, 'D'), (1, 'I'), (1, 'J'), (1, 'K')]);
+
+        let mut f = big_forest();
+        assert!(f.root_mut().detach_subtree().is_empty());
+        let mut cur = f.end_mut();
+        assert!(cur.detach_subtree().is_empty());
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Trailing, None));
+        assert_eq!(f.size(), 11);
+        assert!(f == big_forest());
+    }
 
-        let mut cur = a.begin();
-        assert_eq!(cur.current().map(|(c, _)| c), Some(&'A'));
+    #[test]
+    fn insert_on_leading_and_trailing_edges() {
+        let mut f = Forest::singleton('a');
+        let mut cur = f.begin_mut();
+        cur.insert('s');
+        cur.trailing_of();
+        cur.insert('c');
+        assert_eq!(f.flatten(), [(0, 's'), (0, 'a'), (1, 'c')]);
+
+        let mut f = Forest::singleton('a');
+        f.begin_mut().splice(Forest::singleton('s'));
+        let mut cur = f.end_mut();
+        cur.move_prev();
+        cur.splice(Forest::singleton('c'));
+        assert_eq!(f.flatten(), [(0, 's'), (0, 'a'), (1, 'c')]);
+    }
 
-        cur.move_next();
-        /**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'B'));
+    #[test]
+    fn build() {
+        let f = Forest::build(|b| {
+            b.node('A', |b| {
+                b.node('B', |b| {
+                    b.node('C', |b| {
+                        b.leaf('F').leaf('G').leaf('H');
+                    });
+                    b.node('D', |b| {
+                        b.leaf('I').leaf('J').leaf('K');
+                    });
+                    b.leaf('E');
+                });
+            });
+        });
+        assert!(f == big_forest());
+
+        let mut f = Forest::build(|b| {
+            b.node(1, |_| {}).leaf(2).node(3, |b| {
+                b.leaf(4);
+            });
+        });
+        assert_eq!(f.size(), 4);
+        assert_eq!(f.flatten(), [(0, 1), (0, 2), (0, 3), (1, 4)]);
+        assert!(Forest::<char>::build(|_| {}).is_empty());
+    }
 
-        cur.move_next();
-        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'C'));
+    #[test]
+    fn move_to_sibling() {
+        let mut f = Forest::try_from(vec![
+            (0, 'a'), (1, 'b'), (2, 'c'), (0, 'd'), (0, 'e'), (1, 'f'),
+        ]).unwrap();
+        let mut cur = f.begin();
+        let mut roots = vec![*cur.current().unwrap()];
+        while cur.move_to_next_child() {
+            roots.push(*cur.current().unwrap());
+        }
+        assert_eq!(roots, ['a', 'd', 'e']);
+        while cur.move_to_prev_child() {
+            roots.push(*cur.current().unwrap());
+        }
+        assert_eq!(roots, ['a', 'd', 'e', 'd', 'a']);
+        assert!(!f.root().move_to_next_child());
 
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'F'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'F'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'G'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'G'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'H'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'H'));
+        let mut cur = f.begin_mut();
+        cur.trailing_of();
+        assert!(cur.move_to_next_child());
+        assert_eq!((cur.edge(), cur.current()), (ForestEdge::Leading, Some(&mut 'd')));
+        assert!(cur.move_to_next_child());
+        assert!(!cur.move_to_next_child());
+        assert_eq!(cur.current(), Some(&mut 'e'));
+        assert!(cur.move_to_prev_child());
+        assert!(cur.move_to_prev_child());
+        assert!(!cur.move_to_prev_child());
+        assert_eq!(cur.current(), Some(&mut 'a'));
+        assert!(!f.root_mut().move_to_prev_child());
+    }
 
-        cur.move_next();
-        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'C'));
+    #[test]
+    fn breadth_first() {
+        let f = big_forest();
+        assert_eq!(f.breadth_first().collect::<String>(), "ABCDEFGHIJK");
+
+        let f = Forest::try_from(vec![
+            (0, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (0, 'e'), (1, 'f'), (2, 'g'), (0, 'h'),
+        ]).unwrap();
+        assert_eq!(f.breadth_first().collect::<String>(), "aehbdfcg");
+        assert_eq!(Forest::<char>::new().breadth_first().next(), None);
+    }
 
-        cur.move_next();
-        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'D'));
+    #[test]
+    fn count_at_depth() {
+        let f = Forest::try_from(vec![
+            (0, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (0, 'e'), (1, 'f'), (2, 'g'), (2, 'h'), (0, 'i'),
+        ]).unwrap();
+        assert_eq!([0, 1, 2, 3].map(|d| f.count_at_depth(d)), [3, 3, 3, 0]);
+        assert_eq!([0, 1, 2, 3].map(|d| big_forest().count_at_depth(d)), [1, 1, 3, 6]);
+        assert_eq!(Forest::<char>::new().count_at_depth(0), 0);
+    }
 
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'I'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'I'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'J'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'J'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'K'));
-        cur.move_next();
-        /**//**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'K'));
+    #[test]
+    fn height() {
+        assert_eq!(Forest::<char>::new().height(), 0);
+        assert_eq!("abc".chars().collect::<Forest<_>>().height(), 1);
+        assert_eq!(big_forest().height(), 4);
 
-        cur.move_next();
-        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'D'));
+        let f = Forest::unflatten((0..500).map(|d| (d, d)).collect());
+        assert_eq!(f.height(), 500);
+    }
 
+    #[test]
+    fn cursor_clone() {
+        let f = big_forest();
+        let mut cur = find(&f, 'C');
+        let saved = cur.clone();
         cur.move_next();
-        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'E'));
         cur.move_next();
-        /**//**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'E'));
+        assert_eq!(cur.current(), Some(&'F'));
+        assert_eq!((saved.edge(), saved.current()), (ForestEdge::Leading, Some(&'C')));
+        assert!(saved == find(&f, 'C'));
+        assert_eq!(saved.clone().map(|(_, v)| *v).take(3).collect::<String>(), "CFF");
+    }
 
-        cur.move_next();
-        /**/assert_eq!(cur.current().map(|(c, _)| c), Some(&'B'));
+    #[test]
+    fn swap() {
+        let mut f = big_forest();
+        let mut g: Forest<char> = "xy".chars().collect();
+        g.invalidate_size();
+        f.swap(&mut g);
+        assert_eq!(f.iter().collect::<String>(), "xy");
+        assert!(!f.size_is_cached());
+        assert_eq!(f.size(), 2);
+        assert!(g == big_forest());
+        assert_eq!(g.size(), 11);
+
+        g.swap(&mut Forest::new());
+        assert!(g.is_empty());
+    }
 
-        cur.move_next();
-        assert_eq!(cur.current().map(|(c, _)| c), Some(&'A'));
+    #[test]
+    fn find_and_contains() {
+        let f = big_forest();
+        let d = f.find(|c| *c == 'D').unwrap();
+        assert_eq!(d.edge(), ForestEdge::Leading);
+        assert_eq!(d.children().collect::<String>(), "IJK");
+        assert_eq!(f.find(|c| *c > 'E').unwrap().current(), Some(&'F'));
+        assert!(f.find(|c| *c == 'Z').is_none());
+        assert!(f.contains(&'K'));
+        assert!(!f.contains(&'Z'));
+        assert!(!Forest::new().contains(&'A'));
+    }
 
-        cur.move_next();
-        assert_eq!(cur, a.end());
+    #[test]
+    fn retain() {
+        let mut f = big_forest();
+        f.retain(|c| *c != 'D' && *c != 'G');
+        assert_eq!(f.size(), 6);
+        assert_eq!(f.flatten(), [(0, 'A'), (1, 'B'), (2, 'C'), (3, 'F'), (3, 'H'), (2, 'E')]);
+
+        let mut f = Forest::try_from(vec![(0, 1), (1, 2), (0, 3), (0, 4), (1, 5), (1, 6)]).unwrap();
+        f.retain(|v| v % 2 == 0);
+        assert_eq!(f.flatten(), [(0, 4), (1, 6)]);
+
+        let mut f = big_forest();
+        f.retain(|_| false);
+        assert!(f.is_empty());
+        assert_eq!(f.size(), 0);
+    }
 
-        a.clear();
-        assert!(a.empty());
+    #[test]
+    fn fullorder_edges() {
+        use ForestEdge::*;
+        let f = Forest::try_from(vec![(0, 'a'), (1, 'b'), (1, 'c'), (0, 'd')]).unwrap();
+        assert_eq!(f.fullorder().map(|(e, v)| (e, *v)).collect::<Vec<_>>(), [
+            (Leading, 'a'), (Leading, 'b'), (Trailing, 'b'), (Leading, 'c'), (Trailing, 'c'),
+            (Trailing, 'a'), (Leading, 'd'), (Trailing, 'd'),
+        ]);
+        assert_eq!(big_forest().fullorder().count(), 22);
+        assert_eq!(Forest::<char>::new().fullorder().next(), None);
+    }
 
-        assert!(std::rc::Rc::get_mut(&mut data).is_some());
+    #[test]
+    fn graft() {
+        let mut f = big_forest();
+        let at = find(&f, 'D').id();
+        assert!(f.graft(at, "xy".chars().collect()).is_ok());
+        assert_eq!(f.size(), 13);
+        assert_eq!(find(&f, 'D').children().collect::<String>(), "IJKxy");
+
+        let at = find(&f, 'x').id();
+        assert!(f.graft(at, Forest::singleton('z')).is_ok());
+        assert_eq!(find(&f, 'z').depth(), 4);
+
+        let other = big_forest();
+        let rejected = f.graft(find(&other, 'A').id(), Forest::singleton('w')).unwrap_err();
+        assert_eq!(rejected.flatten(), [(0, 'w')]);
+        assert_eq!(f.size(), 14);
     }
 }